@@ -1,9 +1,9 @@
 use crate::inline_parser::parse_inline;
 use crate::utils::truncate_string;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Dialogue {
     pub character_name: String,
     pub character_extensions: Vec<String>, // The bit in brackets after the character name, eg "WILL (V.O)"
@@ -24,14 +24,38 @@ impl Dialogue {
     pub fn get_num_words(&self) -> usize {
         self.text.split_whitespace().count()
     }
+
+    /// Whether it's the named character's, ignoring case
+    pub fn is_spoken_by(&self, name: &str) -> bool {
+        self.character_name.trim().eq_ignore_ascii_case(name.trim())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SceneHeading {
     pub int_or_ext: String,
     pub text: String,
 }
 
+impl SceneHeading {
+    /// A placeholder for a scene that's been cut since an earlier draft, so
+    /// the scenes after it keep their numbers
+    pub fn omitted() -> SceneHeading {
+        SceneHeading {
+            int_or_ext: String::new(),
+            text: String::from("OMITTED"),
+        }
+    }
+
+    /// The heading as it's printed, eg. "INT. HOUSE - DAY"
+    pub fn heading_text(&self) -> String {
+        match self.int_or_ext.is_empty() {
+            true => self.text.clone(),
+            false => format!("{}. {}", self.int_or_ext, self.text),
+        }
+    }
+}
+
 impl fmt::Display for Dialogue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -54,7 +78,13 @@ impl fmt::Display for SceneHeading {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub depth: usize, // The number of #s, eg. "# Act One" is 1, "## Scene 2" is 2
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
 pub struct Action {
     pub is_centered: bool,
     pub text: String,
@@ -66,11 +96,12 @@ impl Action {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum FarceElement {
     FDialogue(Dialogue),
     FSceneHeading(SceneHeading),
     FAction(Action),
+    FSection(Section),
     FPageBreak,
 }
 
@@ -90,8 +121,7 @@ impl FarceElement {
     pub fn as_html<'a>(&'a self) -> String {
         match self {
             Self::FSceneHeading(scene_heading) => {
-                let scene_heading_str =
-                    format!("{}. {}", scene_heading.int_or_ext, scene_heading.text);
+                let scene_heading_str = scene_heading.heading_text();
                 format!(
                     "<div class=\"scene-heading\">\n<p>{}</p>\n</div>\n\n",
                     scene_heading_str
@@ -111,6 +141,23 @@ impl FarceElement {
                 )
             }
             Self::FPageBreak => "<div class=\"element-pagebreak\"></div>\n\n".to_string(),
+            // Sections are for organising the script, and don't get printed
+            Self::FSection(_) => String::new(),
+        }
+    }
+
+    /// If this is a comic panel heading, like "PANEL 2", the heading and any
+    /// description of the panel that comes with it. The parser sees a heading
+    /// followed by a description as dialogue, or as action if there's a blank
+    /// line in between.
+    pub fn as_panel(&self) -> Option<(&str, &str)> {
+        match self {
+            Self::FAction(action) if !action.is_centered => split_panel_heading(&action.text),
+            Self::FDialogue(dialogue) if dialogue.character_extensions.is_empty() => {
+                split_panel_heading(&dialogue.character_name)
+                    .map(|(heading, _)| (heading, dialogue.text.as_str()))
+            }
+            _ => None,
         }
     }
 
@@ -123,17 +170,62 @@ impl FarceElement {
                 format!("{}{}", dialogue.character_line_as_text(), dialogue.text)
             }
             Self::FAction(action) => action.text.to_string(),
+            Self::FSection(section) => section.text.to_string(),
             Self::FPageBreak => String::new(),
         }
     }
 }
 
-#[derive(Debug)]
+/// A row of a two column AV (audio / visual) script
+#[derive(Debug, Default)]
+pub struct AvRow<'a> {
+    pub video: Vec<&'a FarceElement>, // Scene headings and action
+    pub audio: Vec<&'a FarceElement>, // Dialogue
+    pub page_break: bool,             // The row comes after a "===" page break
+}
+
+/// Split a comic panel heading like "PANEL 2" off the front of some text,
+/// returning the heading and whatever comes after it
+fn split_panel_heading(text: &str) -> Option<(&str, &str)> {
+    let (first_line, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let heading = first_line.trim();
+    let number = heading.strip_prefix("PANEL ")?.trim();
+    match !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()) {
+        true => Some((heading, rest)),
+        false => None,
+    }
+}
+
+/// A page of a comic script, which starts at a "#" section
+#[derive(Debug, Default)]
+pub struct ComicPage<'a> {
+    pub title: Option<&'a str>, // None for anything before the first "#"
+    pub elements: Vec<&'a FarceElement>,
+}
+
+impl ComicPage<'_> {
+    pub fn num_panels(&self) -> usize {
+        self.elements
+            .iter()
+            .filter(|e| e.as_panel().is_some())
+            .count()
+    }
+
+    /// Speech balloons, captions and the like, ie. all the dialogue
+    pub fn num_balloons(&self) -> usize {
+        self.elements
+            .iter()
+            .filter(|e| matches!(e, FarceElement::FDialogue(_)) && e.as_panel().is_none())
+            .count()
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct TitlePage {
     pub fields: HashMap<String, String>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct FarceDocument {
     pub title_page: Option<TitlePage>,
     pub elements: Vec<FarceElement>,
@@ -158,22 +250,95 @@ impl FarceDocument {
         }
     }
 
+    /// Group the elements into AV script rows. Each row is some video
+    /// elements and the dialogue that goes with them, so a new row starts
+    /// whenever video follows audio. Page breaks start a new row too, which
+    /// is marked as starting a new page.
+    pub fn get_av_rows(&self) -> Vec<AvRow<'_>> {
+        let mut rows: Vec<AvRow<'_>> = Vec::new();
+        let mut current = AvRow::default();
+        for e in &self.elements {
+            match e {
+                FarceElement::FDialogue(_) => current.audio.push(e),
+                FarceElement::FSceneHeading(_) | FarceElement::FAction(_) => {
+                    if !current.audio.is_empty() {
+                        rows.push(std::mem::take(&mut current));
+                    }
+                    current.video.push(e);
+                }
+                FarceElement::FPageBreak => {
+                    rows.push(std::mem::take(&mut current));
+                    current.page_break = true;
+                }
+                FarceElement::FSection(_) => {}
+            }
+        }
+        rows.push(current);
+        rows.retain(|row| !row.video.is_empty() || !row.audio.is_empty());
+        rows
+    }
+
+    /// Group the elements into comic pages. Each "#" section starts a new
+    /// page, and any deeper sections are ignored.
+    pub fn get_comic_pages(&self) -> Vec<ComicPage<'_>> {
+        let mut pages: Vec<ComicPage<'_>> = Vec::new();
+        let mut current = ComicPage::default();
+        for e in &self.elements {
+            match e {
+                FarceElement::FSection(section) if section.depth == 1 => {
+                    pages.push(std::mem::take(&mut current));
+                    current.title = Some(&section.text);
+                }
+                FarceElement::FSection(_) => {}
+                _ => current.elements.push(e),
+            }
+        }
+        pages.push(current);
+        pages.retain(|page| page.title.is_some() || !page.elements.is_empty());
+        pages
+    }
+
+    /// The names of all the characters who have dialogue, in order of their
+    /// first appearance
+    pub fn get_character_names(&self) -> Vec<&String> {
+        let mut names = Vec::new();
+        for e in &self.elements {
+            if let FarceElement::FDialogue(dialogue) = e {
+                if !names.contains(&&dialogue.character_name) {
+                    names.push(&dialogue.character_name);
+                }
+            }
+        }
+        names
+    }
+
     pub fn get_all_chars(&self) -> Vec<char> {
         // Get all the chars that appear in the doc, so we know which glyphs
         // we need to embed in the PDF.
         // Maybe we should keep track of the bold / italic chars separately,
         // suspect it wouldn't make a huge difference though.
-        let mut unique_chars = HashSet::new();
+        // They're kept in order, so the fonts get subset the same way every
+        // time.
+        let mut unique_chars = BTreeSet::new();
+        if let Some(title_page) = &self.title_page {
+            for value in title_page.fields.values() {
+                unique_chars.extend(value.chars().filter(|c| *c != '\n'));
+            }
+        }
         for e in &self.elements {
             for c in e.get_all_chars().chars() {
                 if c != '\n' {
                     unique_chars.insert(c);
+                    // Some formats put action in capitals
+                    unique_chars.extend(c.to_uppercase());
                 }
             }
         }
         // Some characters that apear in "boilerplate", but could conceivably
         // not appear in the "text":
         unique_chars.extend("INTEXT._ ()".chars());
+        unique_chars.extend("(MORE)(CONT'D)0123456789".chars());
+        unique_chars.extend("ABCDEFGHIJKLMNOPQRSTUVWXYZ,:".chars()); // Scene letters, radio labels etc.
         unique_chars.into_iter().collect()
     }
 }
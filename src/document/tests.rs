@@ -10,4 +10,43 @@ mod tests {
         let html = document.elements[0].as_html();
         assert_eq!(html, "<div class=\"element-dialogue\">\n<p>FRED (ABC) (EFG)</p>\n<p>Here's some <b>bold</b>, <i>italicized</i>, <b>bold-italicized</b>  and <u>underlined</u> dialogue</p>\n</div>\n\n")
     }
+
+    #[test]
+    fn test_get_av_rows() {
+        let input = "NARRATOR\nIn the beginning.\n\nEXT. A FIELD - DAY\n\nCows graze.\n\nNARRATOR\nCows.\n\nFARMER\nAye.\n\nA tractor.\n";
+        let (_, document) = parse_fountain(input).unwrap();
+        let rows = document.get_av_rows();
+        let counts: Vec<(usize, usize)> = rows
+            .iter()
+            .map(|row| (row.video.len(), row.audio.len()))
+            .collect();
+        assert_eq!(counts, vec![(0, 1), (2, 2), (1, 0)]);
+        assert!(rows.iter().all(|row| !row.page_break));
+
+        let input = "Cows graze.\n\n===\n\nA tractor.\n";
+        let (_, document) = parse_fountain(input).unwrap();
+        let rows = document.get_av_rows();
+        let breaks: Vec<bool> = rows.iter().map(|row| row.page_break).collect();
+        assert_eq!(breaks, vec![false, true]);
+    }
+
+    #[test]
+    fn test_get_comic_pages() {
+        let input = "# Page One\n\nPANEL 1\nA city at night.\n\nCAPTION\nGotham.\n\nPANEL 2\n\nA bat.\n\nBAT\nSqueak.\n\nSFX\nFLAP\n\n## Notes\n\n# Page Two\n\nPANEL 1\nDawn.\n";
+        let (_, document) = parse_fountain(input).unwrap();
+        let pages = document.get_comic_pages();
+        let summary: Vec<(Option<&str>, usize, usize)> = pages
+            .iter()
+            .map(|page| (page.title, page.num_panels(), page.num_balloons()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(Some("Page One"), 2, 3), (Some("Page Two"), 1, 0)]
+        );
+        assert_eq!(
+            pages[0].elements[0].as_panel(),
+            Some(("PANEL 1", "A city at night."))
+        );
+        assert_eq!(pages[0].elements[2].as_panel(), Some(("PANEL 2", "")));
+    }
 }
@@ -1,5 +1,5 @@
 use crate::constants::{DEFAULT_CREDIT, DEFAULT_TITLE};
-use crate::document::{FarceDocument, TitlePage};
+use crate::document::{FarceDocument, FarceElement, TitlePage};
 use std::io::Write;
 
 const HTML_HEADER: &[u8] = b"<html><head><style type=\"text/css\">
@@ -10,6 +10,11 @@ p {padding: 0px;}
 div::after { content: \"\\00a0\";}
 div.element-pagebreak {break-after:page; padding-bottom: 250px; }
 div#title-page-credits {text-align: center; margin: 200px auto 200px auto;}
+table.av {width: 100%; border-collapse: collapse;}
+table.av th {text-align: left;}
+table.av td {vertical-align: top; width: 50%; padding: 0px 20px 20px 0px;}
+table.av .element-dialogue {padding: 0px;}
+.highlighted {background-color: #fff08c;}
 </style></head>\n\n<body>";
 
 const HTML_FOOTER: &[u8] = b"</body></html>";
@@ -41,10 +46,31 @@ fn write_title_page(title_page: &TitlePage, w: &mut impl Write) {
     w.write(b"</div>").unwrap();
 }
 
+/// The element's HTML, with the "highlighted" class if it's the highlighted
+/// character's dialogue
+fn element_html(element: &FarceElement, highlight: Option<&str>) -> String {
+    let html = element.as_html();
+    match element {
+        FarceElement::FDialogue(dialogue)
+            if highlight.is_some_and(|n| dialogue.is_spoken_by(n)) =>
+        {
+            html.replacen(
+                "class=\"element-dialogue\"",
+                "class=\"element-dialogue highlighted\"",
+                1,
+            )
+        }
+        _ => html,
+    }
+}
+
+/// Write the script as HTML, with the `highlight` character's cues and
+/// dialogue (if any) in the "highlighted" class
 pub fn write_html(
     document: FarceDocument,
     mut w: impl Write,
     include_header_and_footer: bool,
+    highlight: Option<&str>,
 ) -> Result<(), String> {
     if include_header_and_footer {
         w.write(HTML_HEADER).unwrap();
@@ -53,10 +79,49 @@ pub fn write_html(
         write_title_page(title_page, &mut w);
     }
     for element in &document.elements {
-        w.write(element.as_html().as_bytes()).unwrap();
+        w.write(element_html(element, highlight).as_bytes())
+            .unwrap();
     }
     if include_header_and_footer {
         w.write(HTML_FOOTER).unwrap();
     }
     Ok(())
 }
+
+/// Like `write_html`, but for a two column AV script, with the video on the
+/// left and the audio on the right.
+pub fn write_av_html(
+    document: FarceDocument,
+    mut w: impl Write,
+    include_header_and_footer: bool,
+    highlight: Option<&str>,
+) -> Result<(), String> {
+    if include_header_and_footer {
+        w.write(HTML_HEADER).unwrap();
+    }
+    if let Some(ref title_page) = document.title_page {
+        write_title_page(title_page, &mut w);
+    }
+    w.write(b"<table class=\"av\">\n<tr><th>VIDEO</th><th>AUDIO</th></tr>\n")
+        .unwrap();
+    for row in document.get_av_rows() {
+        w.write(b"<tr>\n").unwrap();
+        for column in [&row.video, &row.audio] {
+            w.write(b"<td>\n").unwrap();
+            for element in column {
+                w.write(element_html(element, highlight).as_bytes())
+                    .unwrap();
+            }
+            w.write(b"</td>\n").unwrap();
+        }
+        w.write(b"</tr>\n").unwrap();
+    }
+    w.write(b"</table>\n").unwrap();
+    if include_header_and_footer {
+        w.write(HTML_FOOTER).unwrap();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,21 @@
+#[cfg(test)]
+mod tests {
+    use crate::html::write_html;
+    use crate::parser::parse_fountain;
+
+    #[test]
+    fn test_highlight() {
+        let input = "Title: Test\n\nMARY\nHello.\n\nFRED\nHi.\n";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let mut html = Vec::new();
+        write_html(fdoc, &mut html, false, Some("Mary")).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert_eq!(
+            html.matches("class=\"element-dialogue highlighted\"")
+                .count(),
+            1
+        );
+        assert!(html.contains("<div class=\"element-dialogue highlighted\">\n<p>MARY</p>"));
+        assert!(html.contains("<div class=\"element-dialogue\">\n<p>FRED</p>"));
+    }
+}
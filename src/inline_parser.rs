@@ -66,6 +66,19 @@ impl Expression {
             }
         }
     }
+
+    /// The text without any of the formatting
+    pub fn as_text(&self) -> String {
+        match self {
+            Expression::Text(t) => t.to_string(),
+            Expression::Italic(expressions)
+            | Expression::Bold(expressions)
+            | Expression::BoldItalic(expressions)
+            | Expression::Underline(expressions) => {
+                expressions.iter().map(|e| e.as_text()).collect()
+            }
+        }
+    }
 }
 
 fn fenced<'a>(start: &'a str, end: &'a str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
@@ -0,0 +1,1308 @@
+// Pagination for PDF output.
+//
+// genpdf will happily break a paragraph wherever it runs out of room, which
+// isn't good enough for screenplays - eg. dialogue should only break between
+// sentences, with a "(MORE)" / "(CONT'D)" pair either side of the break. So
+// instead we do our own line wrapping and pagination here. Everything is
+// measured in lines and (monospaced) character columns, so nothing in here
+// needs to know about fonts or PDFs. Where things go is up to the layout
+// profile, see the profile module.
+
+use crate::constants;
+use crate::document::{Dialogue, FarceDocument, FarceElement, SceneHeading};
+use crate::inline_parser::{parse_inline, Expression};
+use crate::profile::{ElementLayout, LayoutProfile};
+use crate::revision::Change;
+use std::ops::Range;
+
+pub use lock::PageLock;
+pub use sides::Sides;
+
+// Widow / orphan control
+pub const MIN_LINES_EITHER_SIDE: usize = 2;
+pub const MIN_LINES_AFTER_HEADING: usize = 2;
+
+pub const HEADER_LINES: usize = 3; // The page number, then a couple of blank lines
+
+pub const MORE: &str = "(MORE)";
+pub const CONTD: &str = "CONT'D";
+
+/// The conventions to follow when laying out a script. Where things go on
+/// the page is up to the layout profile.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScriptFormat {
+    Screenplay,
+    Multicam,
+    Stage,
+    Av,
+    Radio { numbering: CueNumbering },
+    Comic,
+}
+
+/// Whether the numbers on a radio script's speeches and sound cues start
+/// again on each page, or at each scene heading
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CueNumbering {
+    PerPage,
+    PerScene,
+}
+
+impl CueNumbering {
+    pub fn from_name(name: &str) -> Option<CueNumbering> {
+        match name {
+            "page" => Some(CueNumbering::PerPage),
+            "scene" => Some(CueNumbering::PerScene),
+            _ => None,
+        }
+    }
+}
+
+impl ScriptFormat {
+    pub fn from_name(name: &str) -> Option<ScriptFormat> {
+        match name {
+            "screenplay" => Some(ScriptFormat::Screenplay),
+            "multicam" => Some(ScriptFormat::Multicam),
+            "stage" => Some(ScriptFormat::Stage),
+            "av" => Some(ScriptFormat::Av),
+            "radio" => Some(ScriptFormat::Radio {
+                numbering: CueNumbering::PerPage,
+            }),
+            "comic" => Some(ScriptFormat::Comic),
+            _ => None,
+        }
+    }
+
+    pub fn default_profile(&self) -> LayoutProfile {
+        match self {
+            ScriptFormat::Screenplay => LayoutProfile::feature(),
+            ScriptFormat::Multicam => LayoutProfile::multicam(),
+            ScriptFormat::Stage => LayoutProfile::stage(),
+            ScriptFormat::Av => LayoutProfile::av(),
+            ScriptFormat::Radio { .. } => LayoutProfile::radio(),
+            ScriptFormat::Comic => LayoutProfile::comic(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TextStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    pub style: TextStyle,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Line {
+    pub indent: usize, // In character columns
+    pub spans: Vec<Span>,
+    pub number: Option<usize>, // A cue number for the left margin, in radio scripts
+    pub revised: bool,         // Changed since the earlier draft, so it gets an asterisk
+    pub highlighted: bool,     // Part of the highlighted character's dialogue
+    pub source: Option<Range<usize>>, // The chars of its element's text that it shows
+}
+
+impl Line {
+    pub fn blank() -> Line {
+        Line::default()
+    }
+
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    fn from_chars(indent: usize, chars: &[StyledChar]) -> Line {
+        let mut spans: Vec<Span> = Vec::new();
+        for (c, style, _) in chars {
+            match spans.last_mut() {
+                Some(span) if span.style == *style => span.text.push(*c),
+                _ => spans.push(Span {
+                    text: c.to_string(),
+                    style: *style,
+                }),
+            }
+        }
+        let sources = chars.iter().filter_map(|(_, _, source)| *source);
+        let source = match (sources.clone().min(), sources.max()) {
+            (Some(first), Some(last)) => Some(first..last + 1),
+            _ => None,
+        };
+        Line {
+            indent,
+            spans,
+            number: None,
+            revised: false,
+            highlighted: false,
+            source,
+        }
+    }
+
+    fn plain(indent: usize, text: &str) -> Line {
+        Line {
+            indent,
+            spans: vec![Span {
+                text: text.to_string(),
+                style: TextStyle::default(),
+            }],
+            number: None,
+            revised: false,
+            highlighted: false,
+            source: None,
+        }
+    }
+}
+
+/// An entry in the PDF outline, for a section or a scene
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+    pub depth: usize, // 1 for the top level
+    pub title: String,
+    pub line: usize,          // Where it goes on the page
+    pub scene: Option<usize>, // The scene's number, if it's a scene's bookmark
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+    pub lines: Vec<Line>,
+    pub bookmarks: Vec<Bookmark>,
+    pub number: Option<String>, // If it isn't just its position, eg. 12A in a locked script
+}
+
+impl Page {
+    /// The page's number, given where it is (counting from 0)
+    pub fn display_number(&self, index: usize) -> String {
+        match &self.number {
+            Some(number) => number.clone(),
+            None => (index + 1).to_string(),
+        }
+    }
+}
+
+/// Where on a page (counting from 0) some lines went. Lines are counted
+/// from the first one under the header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub page: usize,
+    pub line: usize,
+    pub lines: usize,
+}
+
+/// A line on a page, both counting from 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LinePosition {
+    pub page: usize,
+    pub line: usize,
+}
+
+/// The first and last lines of a scene
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SceneSpan {
+    pub scene: usize, // Numbered from 1, as in the outline
+    pub first: LinePosition,
+    pub last: LinePosition,
+}
+
+/// A laid out script: its pages, and where each of its elements went
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    pub pages: Vec<Page>,
+    // For each of the document's elements, where it is on each page it's on.
+    // Empty for anything that isn't printed, like sections.
+    pub placements: Vec<Vec<Placement>>,
+}
+
+impl Layout {
+    /// The last line with anything on it before the given line
+    fn last_line_before(&self, position: LinePosition) -> Option<LinePosition> {
+        let LinePosition { mut page, mut line } = position;
+        loop {
+            while line > 0 {
+                line -= 1;
+                if !self.pages[page].lines[line].spans.is_empty() {
+                    return Some(LinePosition { page, line });
+                }
+            }
+            if page == 0 {
+                return None;
+            }
+            page -= 1;
+            line = self.pages[page].lines.len();
+        }
+    }
+
+    /// Where each scene starts and ends
+    pub fn scenes(&self) -> Vec<SceneSpan> {
+        let starts: Vec<(usize, LinePosition)> = self
+            .pages
+            .iter()
+            .enumerate()
+            .flat_map(|(page, p)| {
+                p.bookmarks.iter().filter_map(move |b| {
+                    let line = b.line;
+                    b.scene.map(|scene| (scene, LinePosition { page, line }))
+                })
+            })
+            .collect();
+        let end = LinePosition {
+            page: self.pages.len(),
+            line: 0,
+        };
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, &(scene, first))| {
+                let next = starts.get(i + 1).map_or(end, |&(_, next)| next);
+                SceneSpan {
+                    scene,
+                    first,
+                    last: self.last_line_before(next).unwrap_or(first).max(first),
+                }
+            })
+            .collect()
+    }
+
+    /// How long a scene is in eighths of a page, as production schedules
+    /// count it: the lines it takes up on each page, to the nearest eighth of
+    /// a full page, and never less than an eighth
+    pub fn eighths(&self, span: &SceneSpan, lines_per_page: usize) -> usize {
+        let lines_per_page = lines_per_page.max(1);
+        let eighths: usize = (span.first.page..=span.last.page)
+            .map(|page| {
+                let first = match page == span.first.page {
+                    true => span.first.line,
+                    false => 0,
+                };
+                let end = match page == span.last.page {
+                    true => span.last.line + 1,
+                    false => self.pages[page].lines.len(),
+                };
+                (end.saturating_sub(first) * 8 + lines_per_page / 2) / lines_per_page
+            })
+            .sum();
+        eighths.max(1)
+    }
+}
+
+/// A char with its style, and its index in its element's text (see
+/// `revision::element_text`), or None if the layout added it, like the
+/// "(MORE)" at a page break
+type StyledChar = (char, TextStyle, Option<usize>);
+
+fn flatten_expressions(
+    expressions: &[Expression],
+    text_style: &mut TextStyle,
+    out: &mut Vec<StyledChar>,
+) {
+    for e in expressions {
+        match e {
+            Expression::Text(t) => {
+                let start = out.len();
+                let chars = t.chars().enumerate();
+                out.extend(chars.map(|(i, c)| (c, *text_style, Some(start + i))));
+            }
+            Expression::Bold(v) => {
+                text_style.bold = true;
+                flatten_expressions(v, text_style, out);
+                text_style.bold = false;
+            }
+            Expression::Italic(v) => {
+                text_style.italic = true;
+                flatten_expressions(v, text_style, out);
+                text_style.italic = false;
+            }
+            Expression::BoldItalic(v) => {
+                text_style.bold = true;
+                text_style.italic = true;
+                flatten_expressions(v, text_style, out);
+                text_style.bold = false;
+                text_style.italic = false;
+            }
+            Expression::Underline(v) => {
+                text_style.underline = true;
+                flatten_expressions(v, text_style, out);
+                text_style.underline = false;
+            }
+        }
+    }
+}
+
+fn styled_chars(text: &str) -> Vec<StyledChar> {
+    let mut chars = Vec::new();
+    match parse_inline(text) {
+        Ok((_remainder, expressions)) => {
+            flatten_expressions(&expressions, &mut TextStyle::default(), &mut chars)
+        }
+        Err(_) => chars.extend(
+            text.chars()
+                .enumerate()
+                .map(|(i, c)| (c, TextStyle::default(), Some(i))),
+        ),
+    }
+    chars
+}
+
+/// Chars that the layout adds, which aren't from the element's text
+fn added_chars(text: &str) -> Vec<StyledChar> {
+    text.chars()
+        .map(|c| (c, TextStyle::default(), None))
+        .collect()
+}
+
+/// A character cue, which comes first in the dialogue's text
+fn cue_chars(dialogue: &Dialogue) -> Vec<StyledChar> {
+    let cue = dialogue.character_line_as_text();
+    let chars = cue.chars().enumerate();
+    chars
+        .map(|(i, c)| (c, TextStyle::default(), Some(i)))
+        .collect()
+}
+
+/// What the character says, which comes after the cue in the dialogue's text
+fn speech_chars(dialogue: &Dialogue) -> Vec<StyledChar> {
+    let start = dialogue.character_line_as_text().chars().count() + 1;
+    let chars = styled_chars(&dialogue.text).into_iter();
+    chars
+        .map(|(c, style, source)| (c, style, source.map(|i| start + i)))
+        .collect()
+}
+
+/// Word-wrap some styled text to the given width, returning the range of
+/// chars that ends up on each line. Whitespace at the wrap points is dropped,
+/// and newlines always start a new line.
+fn wrap(chars: &[StyledChar], width: usize) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    let mut line_end = 0; // End of the last word that fitted on the line
+    let mut line_is_empty = true;
+    let mut i = 0;
+    while i <= chars.len() {
+        if i == chars.len() || chars[i].0 == '\n' {
+            lines.push(line_start..line_end);
+            line_start = i + 1;
+            line_end = i + 1;
+            line_is_empty = true;
+            i += 1;
+            continue;
+        }
+        if chars[i].0 == ' ' {
+            if line_is_empty {
+                line_start = i + 1;
+                line_end = i + 1;
+            }
+            i += 1;
+            continue;
+        }
+        let word_end = (i..chars.len())
+            .find(|&j| chars[j].0 == ' ' || chars[j].0 == '\n')
+            .unwrap_or(chars.len());
+        if word_end - line_start <= width {
+            line_end = word_end;
+            line_is_empty = false;
+            i = word_end;
+        } else if line_is_empty {
+            // A single word that's too long for the line, so chop it up
+            line_end = line_start + width;
+            lines.push(line_start..line_end);
+            line_start = line_end;
+            i = line_end;
+        } else {
+            lines.push(line_start..line_end);
+            line_start = i;
+            line_end = i;
+            line_is_empty = true;
+        }
+    }
+    lines
+}
+
+/// Wrap some text to an element's width, returning the range of each line
+/// along with the element it belongs to.
+fn wrap_element(
+    chars: &[StyledChar],
+    element: ElementLayout,
+) -> Vec<(Range<usize>, ElementLayout)> {
+    wrap(chars, element.width)
+        .into_iter()
+        .map(|r| (r, element))
+        .collect()
+}
+
+/// Turn wrapped text into lines, with blank lines in between if the text is
+/// more than single spaced.
+fn spaced_lines(chars: &[StyledChar], wrapped: Vec<(Range<usize>, ElementLayout)>) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for (i, (r, element)) in wrapped.into_iter().enumerate() {
+        if i > 0 {
+            for _ in 1..element.line_spacing {
+                lines.push(Line::blank());
+            }
+        }
+        let indent = element.line_indent(r.len());
+        lines.push(Line::from_chars(indent, &chars[r]));
+    }
+    lines
+}
+
+/// How many lines some wrapped text takes up, including any line spacing
+fn spaced_len(wrapped: &[(Range<usize>, ElementLayout)]) -> usize {
+    wrapped
+        .iter()
+        .enumerate()
+        .map(|(i, (_, element))| match i {
+            0 => 1,
+            _ => element.line_spacing.max(1),
+        })
+        .sum()
+}
+
+fn wrapped_lines(chars: &[StyledChar], element: ElementLayout) -> Vec<Line> {
+    spaced_lines(chars, wrap_element(chars, element))
+}
+
+fn is_parenthetical(chars: &[StyledChar]) -> bool {
+    let text: String = chars.iter().map(|(c, _, _)| c).collect();
+    let text = text.trim();
+    text.starts_with('(') && text.ends_with(')')
+}
+
+/// Like `wrap`, but for dialogue, where any line that's in brackets is a
+/// parenthetical with its own indent and width.
+fn wrap_dialogue(
+    chars: &[StyledChar],
+    profile: &LayoutProfile,
+) -> Vec<(Range<usize>, ElementLayout)> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for hard_line in chars.split(|(c, _, _)| *c == '\n') {
+        let element = match is_parenthetical(hard_line) {
+            true => profile.parenthetical,
+            false => profile.dialogue,
+        };
+        for (r, element) in wrap_element(hard_line, element) {
+            lines.push((r.start + start..r.end + start, element));
+        }
+        start += hard_line.len() + 1;
+    }
+    lines
+}
+
+/// Positions where we're allowed to break some text between sentences. Each
+/// one is the index just after a sentence's final punctuation.
+fn sentence_boundaries(chars: &[StyledChar]) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    for (i, (c, _, _)) in chars.iter().enumerate() {
+        if !".!?".contains(*c) {
+            continue;
+        }
+        let mut end = i + 1;
+        while end < chars.len() && "\"')]".contains(chars[end].0) {
+            end += 1;
+        }
+        let followed_by_space = end < chars.len() && chars[end].0.is_whitespace();
+        let has_more_text = chars[end..].iter().any(|(c, _, _)| !c.is_whitespace());
+        if followed_by_space && has_more_text && boundaries.last() != Some(&end) {
+            boundaries.push(end);
+        }
+    }
+    boundaries
+}
+
+fn trim_start(chars: &[StyledChar]) -> &[StyledChar] {
+    let start = chars
+        .iter()
+        .position(|(c, _, _)| !c.is_whitespace())
+        .unwrap_or(chars.len());
+    &chars[start..]
+}
+
+fn trim(chars: &[StyledChar]) -> &[StyledChar] {
+    let chars = trim_start(chars);
+    let end = chars
+        .iter()
+        .rposition(|(c, _, _)| !c.is_whitespace())
+        .map_or(0, |i| i + 1);
+    &chars[..end]
+}
+
+/// Transitions are lines like "CUT TO:", which get pushed over to the right
+fn is_transition(text: &str) -> bool {
+    let text = text.trim();
+    !text.contains('\n') && text.ends_with("TO:") && text == text.to_uppercase()
+}
+
+fn contd_cue(dialogue: &Dialogue) -> Vec<StyledChar> {
+    let mut chars = cue_chars(dialogue);
+    if !dialogue.character_extensions.iter().any(|e| e == CONTD) {
+        chars.extend(added_chars(&format!(" ({})", CONTD)));
+    }
+    chars
+}
+
+/// A character cue, or the "(MORE)" that goes in the same place
+fn cue_line(chars: &[StyledChar], profile: &LayoutProfile) -> Line {
+    let indent = profile.character.line_indent(chars.len());
+    Line::from_chars(indent, chars)
+}
+
+/// A chunk of the script that gets laid out as a unit, separated from its
+/// neighbours by a blank line.
+enum Block {
+    SceneHeading(Vec<Line>),
+    Transition(Vec<Line>),
+    Action {
+        chars: Vec<StyledChar>,
+        is_centered: bool,
+    },
+    Dialogue {
+        cue: Vec<StyledChar>,
+        contd_cue: Vec<StyledChar>,
+        chars: Vec<StyledChar>,
+    },
+    Row(Vec<Line>), // Already laid out, but can be split between any lines
+    PageBreak,
+    PageHeading(Vec<Line>), // Goes at the top of every page from here on
+    Bookmark(Bookmark),     // Marks the start of the next block, takes no space
+    Mark(Mark),             // How the lines of the blocks after it get marked
+}
+
+impl Block {
+    /// The block for an element, or None if it doesn't get printed
+    fn from_element(element: &FarceElement, profile: &LayoutProfile) -> Option<Block> {
+        let block = match element {
+            FarceElement::FAction(action) if is_transition(&action.text) => {
+                let mut line = Line::from_chars(0, trim(&styled_chars(&action.text)));
+                let right_edge = profile.transition.right_edge();
+                line.indent = right_edge.saturating_sub(line.text().chars().count());
+                Block::Transition(vec![line])
+            }
+            FarceElement::FAction(action) => Block::Action {
+                chars: styled_chars(&action.text),
+                is_centered: action.is_centered,
+            },
+            FarceElement::FDialogue(dialogue) => Block::Dialogue {
+                cue: cue_chars(dialogue),
+                contd_cue: contd_cue(dialogue),
+                chars: speech_chars(dialogue),
+            },
+            FarceElement::FSceneHeading(scene_heading) => {
+                let text = scene_heading.heading_text();
+                let style = TextStyle {
+                    bold: true,
+                    ..TextStyle::default()
+                };
+                let chars = text.chars().enumerate();
+                let chars: Vec<StyledChar> = chars.map(|(i, c)| (c, style, Some(i))).collect();
+                Block::SceneHeading(wrapped_lines(&chars, profile.scene_heading))
+            }
+            FarceElement::FSection(_) => return None,
+            FarceElement::FPageBreak => Block::PageBreak,
+        };
+        Some(block)
+    }
+
+    fn lines(&self, profile: &LayoutProfile) -> Vec<Line> {
+        match self {
+            Block::SceneHeading(lines) | Block::Transition(lines) | Block::Row(lines) => {
+                lines.clone()
+            }
+            Block::Action { chars, is_centered } => {
+                let mut lines = self.text_lines(chars, profile);
+                if *is_centered {
+                    for line in lines.iter_mut() {
+                        let len = line.text().chars().count();
+                        let margin = profile.action.width.saturating_sub(len) / 2;
+                        line.indent = profile.action.indent + margin;
+                    }
+                }
+                lines
+            }
+            Block::Dialogue { cue, chars, .. } => {
+                let mut lines = vec![cue_line(cue, profile)];
+                lines.extend(self.text_lines(chars, profile));
+                lines
+            }
+            Block::PageBreak | Block::PageHeading(_) | Block::Bookmark(_) | Block::Mark(_) => {
+                Vec::new()
+            }
+        }
+    }
+
+    /// Wrap (some of) the block's text, returning the range of each line
+    /// and the element it belongs to.
+    fn wrap_text(
+        &self,
+        chars: &[StyledChar],
+        profile: &LayoutProfile,
+    ) -> Vec<(Range<usize>, ElementLayout)> {
+        match self {
+            Block::Dialogue { .. } => wrap_dialogue(chars, profile),
+            _ => wrap_element(chars, profile.action),
+        }
+    }
+
+    fn text_lines(&self, chars: &[StyledChar], profile: &LayoutProfile) -> Vec<Line> {
+        spaced_lines(chars, self.wrap_text(chars, profile))
+    }
+
+    /// The text that can be split across pages, if any
+    fn breakable_text(&self) -> Option<&[StyledChar]> {
+        match self {
+            Block::Action {
+                chars,
+                is_centered: false,
+            } => Some(chars),
+            Block::Dialogue { chars, .. } => Some(chars),
+            _ => None,
+        }
+    }
+
+    /// How many lines a page break costs us, on top of the text itself.
+    /// For dialogue that's the cue plus the "(MORE)".
+    fn split_overhead(&self) -> usize {
+        match self {
+            Block::Dialogue { .. } => 2,
+            _ => 0,
+        }
+    }
+
+    /// Split the block before char `split`, returning the lines that go on
+    /// this page and what's left over for the next one.
+    fn split_at(&self, split: usize, profile: &LayoutProfile) -> (Vec<Line>, Block) {
+        match self {
+            Block::Action { chars, .. } => {
+                let rest = Block::Action {
+                    chars: trim_start(&chars[split..]).to_vec(),
+                    is_centered: false,
+                };
+                (self.text_lines(&chars[..split], profile), rest)
+            }
+            Block::Dialogue {
+                cue,
+                contd_cue,
+                chars,
+            } => {
+                let mut lines = vec![cue_line(cue, profile)];
+                lines.extend(self.text_lines(&chars[..split], profile));
+                lines.push(cue_line(&added_chars(MORE), profile));
+                let rest = Block::Dialogue {
+                    cue: contd_cue.clone(),
+                    contd_cue: contd_cue.clone(),
+                    chars: trim_start(&chars[split..]).to_vec(),
+                };
+                (lines, rest)
+            }
+            _ => unreachable!("Tried to split an unbreakable block"),
+        }
+    }
+
+    /// Work out how much of the block can go in the `available` lines left on
+    /// a page. Text is only split between sentences, with at least
+    /// MIN_LINES_EITHER_SIDE lines of it on each page. Returns None if the
+    /// whole thing needs to move to the next page.
+    fn take(
+        &self,
+        available: usize,
+        profile: &LayoutProfile,
+    ) -> Option<(Vec<Line>, Option<Block>)> {
+        let lines = self.lines(profile);
+        if lines.len() <= available {
+            return Some((lines, None));
+        }
+        if let Block::Row(lines) = self {
+            let split = available.min(lines.len().saturating_sub(MIN_LINES_EITHER_SIDE));
+            if split < MIN_LINES_EITHER_SIDE {
+                return None;
+            }
+            return Some(split_row(lines, split));
+        }
+        let chars = self.breakable_text()?;
+        let split = sentence_boundaries(chars).into_iter().rev().find(|&b| {
+            let head = self.wrap_text(&chars[..b], profile);
+            let tail = self.wrap_text(trim_start(&chars[b..]), profile);
+            spaced_len(&head) + self.split_overhead() <= available
+                && head.len() >= MIN_LINES_EITHER_SIDE
+                && tail.len() >= MIN_LINES_EITHER_SIDE
+        })?;
+        let (lines, rest) = self.split_at(split, profile);
+        Some((lines, Some(rest)))
+    }
+
+    /// Like `take`, but for when the block won't fit on a page even by
+    /// itself, so we have to ignore the rules and break it wherever we can.
+    fn force_split(&self, available: usize, profile: &LayoutProfile) -> (Vec<Line>, Option<Block>) {
+        if let Block::Row(lines) = self {
+            return split_row(lines, available.max(1));
+        }
+        match self.breakable_text() {
+            Some(chars) => {
+                let wrapped = self.wrap_text(chars, profile);
+                if wrapped.len() < 2 {
+                    // Nowhere to split it, so it has to overflow the page
+                    return (self.lines(profile), None);
+                }
+                let fits = available.saturating_sub(self.split_overhead());
+                // The first line that doesn't fit, but always keep at least
+                // one line on this page
+                let split_line = (1..wrapped.len())
+                    .find(|&i| spaced_len(&wrapped[..=i]) > fits)
+                    .unwrap_or(wrapped.len() - 1);
+                // At the end of the line before, so that any newline goes
+                // with the rest
+                let (range, _) = &wrapped[split_line - 1];
+                let (lines, rest) = self.split_at(range.end, profile);
+                (lines, Some(rest))
+            }
+            None => (self.lines(profile), None),
+        }
+    }
+}
+
+/// Put the left and right columns side by side, padding the left hand lines
+/// with spaces to get the right hand ones in the right place.
+fn merge_columns(left: Vec<Line>, right: Vec<Line>) -> Vec<Line> {
+    let len = left.len().max(right.len());
+    let mut left = left.into_iter();
+    let mut right = right.into_iter();
+    (0..len)
+        .map(|_| {
+            let mut line = left.next().unwrap_or_default();
+            let right_line = right.next().unwrap_or_default();
+            if right_line.spans.is_empty() {
+                return line;
+            }
+            if line.spans.is_empty() {
+                return right_line;
+            }
+            let used = line.indent + line.text().chars().count();
+            line.spans.push(Span {
+                text: " ".repeat(right_line.indent.saturating_sub(used).max(1)),
+                style: TextStyle::default(),
+            });
+            line.spans.extend(right_line.spans);
+            line.source = match (line.source, right_line.source) {
+                (Some(left), Some(right)) => {
+                    Some(left.start.min(right.start)..left.end.max(right.end))
+                }
+                (left, right) => left.or(right),
+            };
+            line
+        })
+        .collect()
+}
+
+/// A numbered row for radio cues and comic balloons, with the label (eg.
+/// "MARY:") in the character column and the text beside it
+fn numbered_row(
+    label: &[StyledChar],
+    text: Vec<Line>,
+    number: usize,
+    profile: &LayoutProfile,
+) -> Block {
+    let label = Line::from_chars(profile.character.indent, label);
+    let mut lines = merge_columns(vec![label], text);
+    lines[0].number = Some(number);
+    Block::Row(lines)
+}
+
+/// Split a row's lines before line `split`, dropping any blank lines at the
+/// top of the next page.
+fn split_row(lines: &[Line], split: usize) -> (Vec<Line>, Option<Block>) {
+    let rest: Vec<Line> = lines[split..]
+        .iter()
+        .skip_while(|line| line.spans.is_empty())
+        .cloned()
+        .collect();
+    let rest = match rest.is_empty() {
+        true => None,
+        false => Some(Block::Row(rest)),
+    };
+    (lines[..split].to_vec(), rest)
+}
+
+/// Makes the bookmarks for the PDF outline as we go through the document.
+/// Sections nest according to their number of "#"s, and scenes go inside
+/// whichever section they're in.
+#[derive(Default)]
+struct Outline {
+    section_depth: usize,
+    num_scenes: usize,
+}
+
+impl Outline {
+    fn section(&mut self, depth: usize, title: &str) -> Block {
+        self.section_depth = depth;
+        Block::Bookmark(Bookmark {
+            depth,
+            title: title.to_string(),
+            line: 0,
+            scene: None,
+        })
+    }
+
+    fn scene(&mut self, scene_heading: &SceneHeading) -> Block {
+        self.num_scenes += 1;
+        Block::Bookmark(Bookmark {
+            depth: self.section_depth + 1,
+            title: format!("{} {}", self.num_scenes, scene_heading.heading_text()),
+            line: 0,
+            scene: Some(self.num_scenes),
+        })
+    }
+
+    /// The bookmark for an element, if it gets one
+    fn bookmark(&mut self, element: &FarceElement) -> Option<Block> {
+        match element {
+            FarceElement::FSection(section) => Some(self.section(section.depth, &section.text)),
+            FarceElement::FSceneHeading(scene_heading) => Some(self.scene(scene_heading)),
+            _ => None,
+        }
+    }
+}
+
+/// How a block's lines get marked
+#[derive(Clone, Debug, Default)]
+struct Mark {
+    change: Change,       // Since the earlier draft
+    highlighted: bool,    // Whether it's the highlighted character's dialogue
+    elements: Vec<usize>, // The indexes of the elements the lines come from
+}
+
+/// What's changed in each of a document's elements since an earlier draft,
+/// if it's being compared with one, and whose dialogue to highlight
+#[derive(Clone, Copy, Default)]
+struct Marks<'a> {
+    elements: &'a [FarceElement],
+    changes: &'a [Change], // Empty if there's no earlier draft
+    highlight: Option<&'a str>,
+}
+
+impl Marks<'_> {
+    fn index(&self, element: &FarceElement) -> Option<usize> {
+        self.elements.iter().position(|e| std::ptr::eq(e, element))
+    }
+
+    fn change(&self, element: &FarceElement) -> Change {
+        self.index(element)
+            .and_then(|i| self.changes.get(i))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn is_highlighted(&self, element: &FarceElement) -> bool {
+        match (element, self.highlight) {
+            (FarceElement::FDialogue(dialogue), Some(name)) => dialogue.is_spoken_by(name),
+            _ => false,
+        }
+    }
+
+    /// A marker for which elements make up the next block, how they've
+    /// changed, and whether any of them is the highlighted character's
+    /// dialogue. A block made from several elements that haven't all changed
+    /// in the same way gets marked all the way through.
+    fn marker<'e>(&self, elements: impl IntoIterator<Item = &'e FarceElement>) -> Block {
+        let elements: Vec<&FarceElement> = elements.into_iter().collect();
+        let changes: Vec<Change> = elements.iter().map(|e| self.change(e)).collect();
+        let change = match &changes[..] {
+            [change] => change.clone(),
+            changes if changes.iter().all(|c| *c == Change::Unchanged) => Change::Unchanged,
+            _ => Change::Added,
+        };
+        Block::Mark(Mark {
+            change,
+            highlighted: elements.iter().any(|e| self.is_highlighted(e)),
+            elements: elements.iter().filter_map(|e| self.index(e)).collect(),
+        })
+    }
+}
+
+struct Paginator<'a> {
+    profile: &'a LayoutProfile,
+    pages: Vec<Page>,
+    current: Page,
+    bookmarks: Vec<Bookmark>, // Waiting for the next lines to point at
+    page_heading: Vec<Line>,  // For the top of each page, before the blocks
+    mark: Mark,               // For the lines being added
+    lock: Option<&'a PageLock>,
+    anchors: Vec<Option<(usize, usize)>>, // Where each locked page starts in this draft
+    locked_page: usize,                   // The last of the lock's pages that's been started
+    placements: Vec<Vec<Placement>>,
+}
+
+impl<'a> Paginator<'a> {
+    fn new(
+        profile: &'a LayoutProfile,
+        lock: Option<&'a PageLock>,
+        elements: &[FarceElement],
+    ) -> Paginator<'a> {
+        Paginator {
+            profile,
+            pages: Vec::new(),
+            current: Page::default(),
+            bookmarks: Vec::new(),
+            page_heading: Vec::new(),
+            mark: Mark::default(),
+            lock,
+            anchors: lock.map_or(Vec::new(), |lock| lock.anchors(elements)),
+            locked_page: 0,
+            placements: vec![Vec::new(); elements.len()],
+        }
+    }
+
+    /// Where the next lines start: their element (or the first one, for a
+    /// row with several), and how many of its lines have been added already
+    fn anchor(&self) -> Option<(usize, usize)> {
+        let element = *self.mark.elements.iter().min()?;
+        let placed = self.placements[element].iter().map(|p| p.lines).sum();
+        Some((element, placed))
+    }
+
+    /// The locked pages after the last one that's been started, with where
+    /// they start
+    fn next_anchors(&self) -> impl Iterator<Item = (usize, (usize, usize))> + '_ {
+        let anchors = self.anchors.iter().enumerate().skip(self.locked_page + 1);
+        anchors.filter_map(|(i, anchor)| Some((i, (*anchor)?)))
+    }
+
+    /// The locked page that starts with the next lines, if there is one
+    fn find_locked_page(&self) -> Option<usize> {
+        let anchor = self.anchor()?;
+        self.next_anchors()
+            .find(|(_, page_anchor)| *page_anchor == anchor)
+            .map(|(i, _)| i)
+    }
+
+    /// If a locked page starts part way through the next `lines` lines,
+    /// how many of them go before it
+    fn lines_before_locked_page(&self, lines: usize) -> Option<usize> {
+        let (element, placed) = self.anchor()?;
+        self.next_anchors()
+            .filter(|(_, (page_element, _))| *page_element == element)
+            .map(|(_, (_, line))| line.saturating_sub(placed))
+            .find(|&before| before > 0 && before < lines)
+    }
+
+    /// Number a new page in a locked script
+    fn number_page(&mut self) {
+        let lock = match self.lock {
+            Some(lock) => lock,
+            None => return,
+        };
+        let found = self.find_locked_page();
+        let number = match (self.pages.last_mut(), found) {
+            (None, _) => match lock.pages.first() {
+                Some(page) => page.number.clone(),
+                None => String::from("1"),
+            },
+            (Some(previous), Some(i)) => {
+                // Any locked pages in between have been cut, so they're
+                // counted as part of the previous page
+                let skipped = &lock.pages[self.locked_page + 1..i];
+                if let (Some(last), Some(number)) = (skipped.last(), &mut previous.number) {
+                    if *number == lock.pages[self.locked_page].number {
+                        *number = format!("{}-{}", number, last.number);
+                    }
+                }
+                self.locked_page = i;
+                lock.pages[i].number.clone()
+            }
+            (Some(previous), None) => lock::next_page_number(&previous.display_number(0)),
+        };
+        self.current.number = Some(number);
+    }
+
+    /// How many lines are free for a new block, allowing for the blank line
+    /// that separates it from the previous one, or from the page heading.
+    fn available(&self) -> usize {
+        let used = match self.current.lines.len() {
+            0 if self.page_heading.is_empty() => 0,
+            0 => self.page_heading.len() + 1,
+            n => n + 1,
+        };
+        self.profile.lines_per_page.saturating_sub(used)
+    }
+
+    fn new_page(&mut self) {
+        if !self.current.lines.is_empty() {
+            self.pages.push(std::mem::take(&mut self.current));
+        }
+    }
+
+    fn push_lines(&mut self, lines: Vec<Line>) {
+        match self.current.lines.is_empty() {
+            true => {
+                if !lines.is_empty() {
+                    self.number_page();
+                }
+                if !self.page_heading.is_empty() {
+                    self.current.lines.extend(self.page_heading.iter().cloned());
+                    self.current.lines.push(Line::blank());
+                }
+            }
+            false => self.current.lines.push(Line::blank()),
+        }
+        for mut bookmark in self.bookmarks.drain(..) {
+            bookmark.line = self.current.lines.len();
+            self.current.bookmarks.push(bookmark);
+        }
+        self.place(self.current.lines.len(), lines.len());
+        for mut line in lines {
+            line.revised = self.mark.change.is_revised(line.source.as_ref());
+            line.highlighted = self.mark.highlighted;
+            self.current.lines.push(line);
+        }
+    }
+
+    /// Scene headings need to be kept with the start of whatever follows
+    /// them, so check that enough of the next block will fit on this page.
+    fn has_room_for_heading(&self, heading: &[Line], next: Option<&Block>) -> bool {
+        let available = self.available();
+        if heading.len() > available {
+            return false;
+        }
+        let next = match next {
+            Some(Block::PageBreak)
+            | Some(Block::PageHeading(_))
+            | Some(Block::SceneHeading(_))
+            | Some(Block::Bookmark(_)) => return true,
+            None => return true,
+            Some(next) => next,
+        };
+        let available_after = available.saturating_sub(heading.len() + 1);
+        let needed = next.lines(self.profile).len().min(MIN_LINES_AFTER_HEADING);
+        match next.take(available_after, self.profile) {
+            Some((lines, _)) => lines.len() >= needed,
+            None => false,
+        }
+    }
+
+    fn push(&mut self, block: Block, next: Option<&Block>) {
+        if let Block::SceneHeading(lines) = &block {
+            if !self.has_room_for_heading(lines, next) {
+                self.new_page();
+            }
+        }
+        let mut block = block;
+        loop {
+            let num_lines = block.lines(self.profile).len();
+            let taken = match self.lines_before_locked_page(num_lines) {
+                // A locked page that starts part way through the block (eg.
+                // with a "(CONT'D)") has to start in the same place
+                Some(before) if before <= self.available() => {
+                    Some(block.force_split(before, self.profile))
+                }
+                _ => block.take(self.available(), self.profile),
+            };
+            let (lines, rest) = match taken {
+                Some(taken) => taken,
+                None if self.current.lines.is_empty() => {
+                    block.force_split(self.available(), self.profile)
+                }
+                None => {
+                    self.new_page();
+                    continue;
+                }
+            };
+            // A locked page always starts a new page, even if there's room
+            // for it on this one
+            if !self.current.lines.is_empty() && self.find_locked_page().is_some() {
+                self.new_page();
+                continue;
+            }
+            self.push_lines(lines);
+            match rest {
+                Some(rest) => {
+                    self.new_page();
+                    block = rest;
+                }
+                None => return,
+            }
+        }
+    }
+
+    /// Note where the lines being added to the current page came from
+    fn place(&mut self, line: usize, lines: usize) {
+        let page = self.pages.len();
+        for &element in &self.mark.elements {
+            let placements = &mut self.placements[element];
+            match placements.last_mut() {
+                Some(last) if last.page == page => last.lines = line + lines - last.line,
+                _ => placements.push(Placement { page, line, lines }),
+            }
+        }
+    }
+
+    fn finish(mut self) -> Layout {
+        self.new_page();
+        // Anything left over (eg. an empty section at the end) goes at the
+        // bottom of the last page
+        if let Some(last) = self.pages.last_mut() {
+            for mut bookmark in self.bookmarks.drain(..) {
+                bookmark.line = last.lines.len().saturating_sub(1);
+                last.bookmarks.push(bookmark);
+            }
+        }
+        Layout {
+            pages: self.pages,
+            placements: self.placements,
+        }
+    }
+}
+
+/// Anything else to do while laying out a script, besides following the
+/// format's own rules
+#[derive(Clone, Copy, Default)]
+pub struct PaginateOptions<'a> {
+    pub changes: &'a [Change], // What's changed in each element since an earlier draft
+    pub lock: Option<&'a PageLock>, // A locked script's page breaks, to keep
+    pub highlight: Option<&'a str>, // The character whose dialogue gets highlighted
+}
+
+/// Lay out a document's elements into pages, according to the given format
+/// and profile. The title page isn't included.
+pub fn paginate(
+    fountain_doc: &FarceDocument,
+    format: ScriptFormat,
+    profile: &LayoutProfile,
+) -> Vec<Page> {
+    paginate_with(fountain_doc, format, profile, PaginateOptions::default())
+}
+
+/// Like `paginate`, but with the lines marked that have changed since an
+/// earlier draft or are the highlighted character's, and keeping the page
+/// breaks of a locked script
+pub fn paginate_with(
+    fountain_doc: &FarceDocument,
+    format: ScriptFormat,
+    profile: &LayoutProfile,
+    options: PaginateOptions,
+) -> Vec<Page> {
+    lay_out(fountain_doc, format, profile, options).pages
+}
+
+/// Like `paginate_with`, but with where each element went as well
+pub fn lay_out(
+    fountain_doc: &FarceDocument,
+    format: ScriptFormat,
+    profile: &LayoutProfile,
+    options: PaginateOptions,
+) -> Layout {
+    let marks = Marks {
+        elements: &fountain_doc.elements,
+        changes: options.changes,
+        highlight: options.highlight,
+    };
+    let blocks = match format {
+        ScriptFormat::Screenplay => {
+            let mut outline = Outline::default();
+            let mut blocks = Vec::new();
+            for element in &fountain_doc.elements {
+                blocks.push(marks.marker([element]));
+                blocks.extend(outline.bookmark(element));
+                blocks.extend(Block::from_element(element, profile));
+            }
+            blocks
+        }
+        ScriptFormat::Multicam => multicam::blocks(fountain_doc, marks, profile),
+        ScriptFormat::Stage => stage::blocks(fountain_doc, marks, profile),
+        ScriptFormat::Av => av::blocks(fountain_doc, marks, profile),
+        ScriptFormat::Radio { numbering } => radio::blocks(fountain_doc, marks, numbering, profile),
+        ScriptFormat::Comic => comic::blocks(fountain_doc, marks, profile),
+    };
+    // Each block goes with the last marker before it, so the markers don't
+    // get in the way of looking at the next block
+    let mut mark = Mark::default();
+    let blocks: Vec<(Mark, Block)> = blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Mark(next) => {
+                mark = next;
+                None
+            }
+            block => Some((mark.clone(), block)),
+        })
+        .collect();
+    let mut paginator = Paginator::new(profile, options.lock, &fountain_doc.elements);
+    let mut blocks = blocks.into_iter().peekable();
+    while let Some((mark, block)) = blocks.next() {
+        paginator.mark = mark;
+        match block {
+            Block::PageBreak => paginator.new_page(),
+            Block::PageHeading(lines) => paginator.page_heading = lines,
+            Block::Bookmark(bookmark) => paginator.bookmarks.push(bookmark),
+            block => paginator.push(block, blocks.peek().map(|(_, next)| next)),
+        }
+    }
+    let mut layout = paginator.finish();
+    if let ScriptFormat::Radio {
+        numbering: CueNumbering::PerPage,
+    } = format
+    {
+        radio::renumber_per_page(&mut layout.pages);
+    }
+    layout
+}
+
+/// Text centered over the action, eg. on the title page
+fn centered_lines(text: &str, profile: &LayoutProfile) -> Vec<Line> {
+    let centered = ElementLayout {
+        centered: true,
+        ..profile.action
+    };
+    wrapped_lines(&styled_chars(text), centered)
+}
+
+fn title_page(fountain_doc: &FarceDocument, profile: &LayoutProfile) -> Page {
+    let title = fountain_doc
+        .get_title()
+        .map_or(constants::DEFAULT_TITLE, String::as_str);
+    let mut lines = vec![Line::blank(); 10];
+    lines.extend(centered_lines(title, profile));
+    if let Some(author) = fountain_doc.get_titlepage_field("Author") {
+        let credit = fountain_doc
+            .get_titlepage_field("Credit")
+            .map_or(constants::DEFAULT_CREDIT, String::as_str);
+        lines.push(Line::blank());
+        lines.extend(centered_lines(credit, profile));
+        lines.push(Line::blank());
+        lines.extend(centered_lines(author, profile));
+    }
+    Page {
+        lines,
+        ..Page::default()
+    }
+}
+
+/// The pages that go before the script: the title page, if there is one,
+/// and for a stage play the list of characters. They're on the same grid as
+/// the script, but they don't have a header, so their lines are counted from
+/// the top margin.
+pub fn front_pages(
+    fountain_doc: &FarceDocument,
+    format: ScriptFormat,
+    profile: &LayoutProfile,
+) -> Vec<Page> {
+    let mut pages = Vec::new();
+    if fountain_doc.has_title_page() {
+        pages.push(title_page(fountain_doc, profile));
+    }
+    if format == ScriptFormat::Stage {
+        pages.extend(stage::cast_pages(fountain_doc, profile));
+    }
+    pages
+}
+
+mod av;
+mod comic;
+mod lock;
+mod multicam;
+mod radio;
+mod sides;
+mod stage;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,67 @@
+// Two column AV (audio / visual) scripts, as used for documentaries and
+// commercials. Scene headings and action go in the video column on the
+// left, and dialogue in the audio column on the right, lined up row by row,
+// with the column headings at the top of every page.
+// Where the columns go is up to the layout profile.
+
+use super::{merge_columns, Block, Line, Marks, Outline, TextStyle};
+use crate::document::{FarceDocument, FarceElement};
+use crate::profile::LayoutProfile;
+
+/// Lay out some elements one after the other, with blank lines in between
+fn column_lines(elements: &[&FarceElement], profile: &LayoutProfile) -> Vec<Line> {
+    let mut lines = Vec::new();
+    for element in elements {
+        if let Some(block) = Block::from_element(element, profile) {
+            if !lines.is_empty() {
+                lines.push(Line::blank());
+            }
+            lines.extend(block.lines(profile));
+        }
+    }
+    lines
+}
+
+fn column_headings(profile: &LayoutProfile) -> Vec<Line> {
+    let style = TextStyle {
+        bold: true,
+        underline: true,
+        ..TextStyle::default()
+    };
+    let heading = |indent, text: &str| {
+        let mut line = Line::plain(indent, text);
+        line.spans[0].style = style;
+        line
+    };
+    merge_columns(
+        vec![heading(profile.action.indent, "VIDEO")],
+        vec![heading(profile.dialogue.indent, "AUDIO")],
+    )
+}
+
+pub(super) fn blocks(
+    fountain_doc: &FarceDocument,
+    marks: Marks,
+    profile: &LayoutProfile,
+) -> Vec<Block> {
+    let mut blocks = vec![Block::PageHeading(column_headings(profile))];
+    let mut outline = Outline::default();
+    for row in fountain_doc.get_av_rows() {
+        if row.page_break {
+            blocks.push(Block::PageBreak);
+        }
+        blocks.push(marks.marker(row.video.iter().chain(&row.audio).copied()));
+        for element in &row.video {
+            blocks.extend(outline.bookmark(element));
+        }
+        let lines = merge_columns(
+            column_lines(&row.video, profile),
+            column_lines(&row.audio, profile),
+        );
+        blocks.push(Block::Row(lines));
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::{paginate, Page, ScriptFormat};
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+
+    fn page_text(page: &Page) -> Vec<String> {
+        page.lines
+            .iter()
+            .map(|l| format!("{}{}", " ".repeat(l.indent), l.text()))
+            .collect()
+    }
+
+    #[test]
+    fn test_av_pagination() {
+        let input = "Title: Test
+
+EXT. A FIELD - DAY
+
+Cows graze.
+
+NARRATOR
+Cows have been grazing here for a thousand years.
+
+A tractor.
+";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Av, &LayoutProfile::av());
+        assert_eq!(pages.len(), 1);
+        let text = page_text(&pages[0]);
+        assert_eq!(
+            text,
+            vec![
+                "VIDEO                           AUDIO",
+                "",
+                "EXT. A FIELD - DAY              NARRATOR",
+                "                                Cows have been grazing here",
+                "Cows graze.                     for a thousand years.",
+                "",
+                "A tractor.",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_av_page_breaks() {
+        let input = "Title: Test
+
+Cows graze.
+
+NARRATOR
+Cows.
+
+===
+
+A tractor.
+";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Av, &LayoutProfile::av());
+        assert_eq!(pages.len(), 2);
+        assert_eq!(
+            pages[0].lines[2].text(),
+            "Cows graze.                     NARRATOR"
+        );
+        assert_eq!(
+            page_text(&pages[1]),
+            vec!["VIDEO                           AUDIO", "", "A tractor."]
+        );
+    }
+
+    #[test]
+    fn test_av_rows_break_across_pages() {
+        let mut input = String::from("Title: Test\n\n");
+        for _ in 0..12 {
+            input.push_str("Something happens.\n\n");
+        }
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let profile = LayoutProfile {
+            lines_per_page: 15,
+            ..LayoutProfile::av()
+        };
+        let pages = paginate(&fdoc, ScriptFormat::Av, &profile);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].lines.len(), 15);
+        // The column headings are repeated on every page
+        assert_eq!(
+            page_text(&pages[1])[0],
+            "VIDEO                           AUDIO"
+        );
+        assert!(pages[1].lines[0].spans[0].style.underline);
+        assert_eq!(pages[1].lines[2].text(), "Something happens.");
+    }
+}
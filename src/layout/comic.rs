@@ -0,0 +1,105 @@
+// Comic script conventions. Each "#" section is a comic page, headed with
+// its number of panels, and "PANEL 1" etc. lines start the panels. The
+// dialogue is what gets lettered, so every balloon, caption and sound
+// effect is numbered, starting again on each page.
+
+use super::{
+    added_chars, cue_chars, numbered_row, spaced_lines, speech_chars, styled_chars, trim,
+    wrap_dialogue, wrapped_lines, Block, Line, Marks, Outline, StyledChar, TextStyle,
+};
+use crate::document::{ComicPage, FarceDocument, FarceElement};
+use crate::profile::LayoutProfile;
+
+fn heading_lines(chars: &[StyledChar], style: TextStyle, profile: &LayoutProfile) -> Vec<Line> {
+    let chars: Vec<StyledChar> = chars
+        .iter()
+        .map(|&(c, _, source)| (c, style, source))
+        .collect();
+    wrapped_lines(&chars, profile.scene_heading)
+}
+
+/// A panel's heading and description, as they are in the element's text
+fn panel_chars(element: &FarceElement) -> (Vec<StyledChar>, Vec<StyledChar>) {
+    let (heading, description) = match element {
+        FarceElement::FDialogue(dialogue) => (cue_chars(dialogue), speech_chars(dialogue)),
+        FarceElement::FAction(action) => {
+            let chars = styled_chars(&action.text);
+            match chars.iter().position(|(c, _, _)| *c == '\n') {
+                Some(end) => (chars[..end].to_vec(), chars[end + 1..].to_vec()),
+                None => (chars, Vec::new()),
+            }
+        }
+        _ => (Vec::new(), Vec::new()),
+    };
+    (trim(&heading).to_vec(), trim(&description).to_vec())
+}
+
+/// "# Page One" becomes "PAGE ONE (3 PANELS)", in bold and underlined
+fn page_heading(title: &str, page: &ComicPage, profile: &LayoutProfile) -> Block {
+    let panels = match page.num_panels() {
+        1 => "1 PANEL".to_string(),
+        n => format!("{} PANELS", n),
+    };
+    let text = format!("{} ({})", title.to_uppercase(), panels);
+    let style = TextStyle {
+        bold: true,
+        underline: true,
+        ..TextStyle::default()
+    };
+    Block::SceneHeading(heading_lines(&added_chars(&text), style, profile))
+}
+
+fn panel_heading(chars: &[StyledChar], profile: &LayoutProfile) -> Block {
+    let style = TextStyle {
+        bold: true,
+        ..TextStyle::default()
+    };
+    Block::SceneHeading(heading_lines(chars, style, profile))
+}
+
+pub(super) fn blocks(
+    fountain_doc: &FarceDocument,
+    marks: Marks,
+    profile: &LayoutProfile,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut outline = Outline::default();
+    for page in fountain_doc.get_comic_pages() {
+        if let Some(title) = page.title {
+            blocks.push(marks.marker([]));
+            blocks.push(outline.section(1, title));
+            blocks.push(page_heading(title, &page, profile));
+        }
+        let mut balloon = 0;
+        for element in &page.elements {
+            blocks.push(marks.marker([*element]));
+            blocks.extend(outline.bookmark(element));
+            if element.as_panel().is_some() {
+                let (heading, description) = panel_chars(element);
+                blocks.push(panel_heading(&heading, profile));
+                if !description.is_empty() {
+                    blocks.push(Block::Action {
+                        chars: description,
+                        is_centered: false,
+                    });
+                }
+                continue;
+            }
+            match element {
+                FarceElement::FDialogue(dialogue) => {
+                    balloon += 1;
+                    let mut label = cue_chars(dialogue);
+                    label.extend(added_chars(":"));
+                    let chars = speech_chars(dialogue);
+                    let text = spaced_lines(&chars, wrap_dialogue(&chars, profile));
+                    blocks.push(numbered_row(&label, text, balloon, profile));
+                }
+                element => blocks.extend(Block::from_element(element, profile)),
+            }
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests;
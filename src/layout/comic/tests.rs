@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::{paginate, ScriptFormat};
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+
+    #[test]
+    fn test_comic_pagination() {
+        let input = "Title: Test
+
+# Page One
+
+PANEL 1
+Night. A city skyline.
+
+CAPTION
+Gotham.
+
+PANEL 2
+
+A bat flaps past the moon.
+
+SFX
+FLAP
+
+# Page Two
+
+PANEL 1
+Dawn.
+
+BAT
+(whispering)
+Home.
+";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Comic, &LayoutProfile::comic());
+        assert_eq!(pages.len(), 1);
+        let text: Vec<String> = pages[0]
+            .lines
+            .iter()
+            .map(|l| format!("{}{}", " ".repeat(l.indent), l.text()))
+            .collect();
+        assert_eq!(
+            text,
+            vec![
+                "PAGE ONE (2 PANELS)",
+                "",
+                "PANEL 1",
+                "",
+                "Night. A city skyline.",
+                "",
+                "    CAPTION:        Gotham.",
+                "",
+                "PANEL 2",
+                "",
+                "A bat flaps past the moon.",
+                "",
+                "    SFX:            FLAP",
+                "",
+                "PAGE TWO (1 PANEL)",
+                "",
+                "PANEL 1",
+                "",
+                "Dawn.",
+                "",
+                "    BAT:              (whispering)",
+                "                    Home.",
+            ]
+        );
+        let first = &pages[0].lines[0].spans[0].style;
+        assert!(first.bold && first.underline);
+        let numbers: Vec<usize> = pages[0].lines.iter().filter_map(|l| l.number).collect();
+        assert_eq!(numbers, vec![1, 2, 1]);
+    }
+}
@@ -0,0 +1,141 @@
+// Locked pages. Once a shooting script is locked, its page numbers mustn't
+// change, so the page breaks are saved in a sidecar file, eg:
+//
+//     [[page]]
+//     number = "12"
+//     element = 143
+//     line = 0
+//     first_line = "INT. A HOUSE - DAY"
+//
+// Each page is anchored to the element at the top of it (counting from 0),
+// and how many of that element's lines were on the pages before. When a
+// later draft is laid out with the lock, the anchors are found again by
+// their elements' first lines, as near as possible to where they were, and
+// each one still starts a new page. Anything that doesn't fit goes on A
+// pages (12A, 12B...), and a page whose text has all been cut is kept as
+// part of the page before (12-13).
+
+use super::Layout;
+use crate::document::{FarceDocument, FarceElement};
+use crate::revision::element_text;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Where one of the locked script's pages started
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct LockedPage {
+    pub number: String,
+    pub element: Option<usize>, // The element at the top of the page, if any
+    pub line: usize,            // How many of the element's lines were on earlier pages
+    pub first_line: String,     // The element's, to find it again in a later draft
+}
+
+/// The page breaks of a locked script
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct PageLock {
+    #[serde(default, rename = "page")]
+    pub pages: Vec<LockedPage>,
+}
+
+/// The number of the page after `number` that's been added to a locked
+/// script, eg. 12A after 12, or 12B after 12A
+pub(super) fn next_page_number(number: &str) -> String {
+    match number.chars().last() {
+        Some(c) if c.is_ascii_uppercase() && c != 'Z' => {
+            format!("{}{}", &number[..number.len() - 1], (c as u8 + 1) as char)
+        }
+        _ => format!("{}A", number),
+    }
+}
+
+impl PageLock {
+    /// Lock a script's pages where they are now
+    pub fn from_layout(layout: &Layout, fountain_doc: &FarceDocument) -> PageLock {
+        let pages = layout
+            .pages
+            .iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let top = page_top(layout, i);
+                LockedPage {
+                    number: page.display_number(i),
+                    element: top.map(|(element, _)| element),
+                    line: top.map_or(0, |(_, line)| line),
+                    first_line: top
+                        .and_then(|(element, _)| fountain_doc.elements.get(element))
+                        .map_or(String::new(), first_line),
+                }
+            })
+            .collect();
+        PageLock { pages }
+    }
+
+    /// Where each locked page starts in a (possibly later) draft, as the
+    /// element and how many of its lines come before, or None if its element
+    /// isn't there any more. Each anchor is looked for in order, nearest to
+    /// where it was, allowing for anything added or cut before it.
+    pub(super) fn anchors(&self, elements: &[FarceElement]) -> Vec<Option<(usize, usize)>> {
+        let first_lines: Vec<String> = elements.iter().map(first_line).collect();
+        let mut shift = 0_isize;
+        let mut start = 0;
+        self.pages
+            .iter()
+            .map(|page| {
+                let expected = page.element? as isize + shift;
+                let found = (start..elements.len())
+                    .filter(|&i| first_lines[i] == page.first_line)
+                    .min_by_key(|&i| (i as isize - expected).abs())?;
+                shift = found as isize - page.element? as isize;
+                start = found;
+                Some((found, page.line))
+            })
+            .collect()
+    }
+
+    pub fn from_toml(input: &str) -> Result<PageLock, String> {
+        toml::from_str(input).map_err(|e| format!("Invalid page lock file ({})", e))
+    }
+
+    pub fn from_toml_file(path: &Path) -> Result<PageLock, String> {
+        let input = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read page lock file {} ({})", path.display(), e))?;
+        PageLock::from_toml(&input)
+    }
+
+    pub fn to_toml_file(&self, path: &Path) -> Result<(), String> {
+        let output =
+            toml::to_string(self).map_err(|e| format!("Couldn't save the page lock ({})", e))?;
+        fs::write(path, output)
+            .map_err(|e| format!("Couldn't write page lock file {} ({})", path.display(), e))
+    }
+}
+
+/// The element at the top of a page, and how many of its lines were on the
+/// pages before. If a row has several elements, it's the first of them.
+fn page_top(layout: &Layout, page: usize) -> Option<(usize, usize)> {
+    let (element, _) = layout
+        .placements
+        .iter()
+        .enumerate()
+        .filter_map(|(element, placements)| {
+            let placement = placements.iter().find(|p| p.page == page)?;
+            Some((element, placement.line))
+        })
+        .min_by_key(|&(element, line)| (line, element))?;
+    let before = layout.placements[element]
+        .iter()
+        .filter(|p| p.page < page)
+        .map(|p| p.lines)
+        .sum();
+    Some((element, before))
+}
+
+/// The first line of an element's text, eg. a speech's character cue, which
+/// is how it's found again in a later draft
+fn first_line(element: &FarceElement) -> String {
+    let text: String = element_text(element).into_iter().collect();
+    text.lines().next().unwrap_or_default().trim().to_string()
+}
@@ -0,0 +1,207 @@
+// Multi-camera sitcom conventions. Each scene starts on a new page, with a
+// scene letter above the heading and the characters in the scene listed
+// below it. Action is in capitals, with character entrances and exits
+// underlined. Double spaced dialogue is up to the layout profile.
+
+use super::{
+    added_chars, is_transition, styled_chars, wrapped_lines, Block, Line, Marks, Outline,
+    StyledChar, TextStyle,
+};
+use crate::document::{FarceDocument, FarceElement, SceneHeading};
+use crate::profile::LayoutProfile;
+
+const ENTRANCE_WORDS: [&str; 4] = ["ENTERS", "ENTER", "EXITS", "EXIT"];
+
+/// Scene letters go A to Z, then AA, BB etc.
+fn scene_letter(index: usize) -> String {
+    let letter = (b'A' + (index % 26) as u8) as char;
+    letter.to_string().repeat(index / 26 + 1)
+}
+
+/// The characters who speak in each scene, in order of appearance. Anything
+/// before the first scene heading isn't counted.
+fn scene_casts(fountain_doc: &FarceDocument) -> Vec<Vec<String>> {
+    let mut casts: Vec<Vec<String>> = Vec::new();
+    for element in &fountain_doc.elements {
+        match element {
+            FarceElement::FSceneHeading(_) => casts.push(Vec::new()),
+            FarceElement::FDialogue(dialogue) => {
+                if let Some(cast) = casts.last_mut() {
+                    if !cast.contains(&dialogue.character_name) {
+                        cast.push(dialogue.character_name.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    casts
+}
+
+fn to_uppercase(chars: &[StyledChar]) -> Vec<StyledChar> {
+    chars
+        .iter()
+        .flat_map(|&(c, style, source)| c.to_uppercase().map(move |u| (u, style, source)))
+        .collect()
+}
+
+fn is_word_char(chars: &[StyledChar], i: usize) -> bool {
+    chars.get(i).is_some_and(|(c, _, _)| c.is_alphanumeric())
+}
+
+/// If one of `words` starts at `i` (and doesn't run on into a longer word),
+/// return the index just after it.
+fn match_word(chars: &[StyledChar], i: usize, words: &[String]) -> Option<usize> {
+    words.iter().find_map(|word| {
+        let end = i + word.chars().count();
+        let runs_on = word.ends_with(char::is_alphanumeric) && is_word_char(chars, end);
+        let matches = end <= chars.len()
+            && chars[i..end].iter().map(|(c, _, _)| *c).eq(word.chars())
+            && !runs_on;
+        matches.then_some(end)
+    })
+}
+
+/// Names written in capitals in the action, which is how characters who
+/// don't speak are usually introduced, eg "THE WAITER" in "THE WAITER ENTERS".
+fn capitalized_names(chars: &[StyledChar]) -> Vec<String> {
+    let text: String = chars.iter().map(|(c, _, _)| c).collect();
+    let mut names = Vec::new();
+    let mut name: Vec<&str> = Vec::new();
+    for word in text.split_whitespace() {
+        let bare = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+        let is_name_word = bare.chars().any(char::is_alphabetic)
+            && !bare.chars().any(char::is_lowercase)
+            && bare != "AND"
+            && !ENTRANCE_WORDS.contains(&bare);
+        if is_name_word {
+            name.push(bare);
+        }
+        // Punctuation after a word ends the name too, eg "FRED, BARNEY"
+        if (!is_name_word || bare != word) && !name.is_empty() {
+            names.push(name.join(" "));
+            name.clear();
+        }
+    }
+    if !name.is_empty() {
+        names.push(name.join(" "));
+    }
+    names
+}
+
+/// Longest first, so that eg. "FRED JR" matches before "FRED" does
+fn sort_names(names: &mut Vec<String>) {
+    names.sort_by_key(|n| (std::cmp::Reverse(n.chars().count()), n.clone()));
+    names.dedup();
+}
+
+/// Underline entrances and exits, eg "FRED ENTERS" or "FRED AND WILMA EXIT".
+/// Expects the text to be in capitals already.
+fn underline_entrances(chars: &mut [StyledChar], names: &[String]) {
+    let separators: Vec<String> = [", ", " AND ", " & "]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let entrance_words: Vec<String> = ENTRANCE_WORDS.iter().map(|s| format!(" {}", s)).collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if i > 0 && is_word_char(chars, i - 1) {
+            i += 1;
+            continue;
+        }
+        // A list of one or more names, followed by one of the entrance words
+        let mut end = None;
+        let mut pos = i;
+        while let Some(name_end) = match_word(chars, pos, names) {
+            end = Some(name_end);
+            match match_word(chars, name_end, &separators) {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+        match end.and_then(|end| match_word(chars, end, &entrance_words)) {
+            Some(end) => {
+                for (_, style, _) in &mut chars[i..end] {
+                    style.underline = true;
+                }
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+}
+
+fn heading_lines(
+    scene_heading: &SceneHeading,
+    scene_index: usize,
+    cast: &[String],
+    profile: &LayoutProfile,
+) -> Vec<Line> {
+    let scene = format!("SCENE {}", scene_letter(scene_index));
+    let margin = profile.action.width.saturating_sub(scene.chars().count()) / 2;
+    let mut lines = vec![
+        Line::plain(profile.action.indent + margin, &scene),
+        Line::blank(),
+    ];
+    let text = scene_heading.heading_text();
+    let style = TextStyle {
+        bold: true,
+        underline: true,
+        ..TextStyle::default()
+    };
+    let chars = text.chars().enumerate();
+    let chars: Vec<StyledChar> = chars.map(|(i, c)| (c, style, Some(i))).collect();
+    lines.extend(wrapped_lines(&chars, profile.scene_heading));
+    if !cast.is_empty() {
+        let cast_list = format!("({})", cast.join(", "));
+        lines.extend(wrapped_lines(
+            &added_chars(&cast_list),
+            profile.scene_heading,
+        ));
+    }
+    lines
+}
+
+pub(super) fn blocks(
+    fountain_doc: &FarceDocument,
+    marks: Marks,
+    profile: &LayoutProfile,
+) -> Vec<Block> {
+    let casts = scene_casts(fountain_doc);
+    let mut names: Vec<String> = casts.iter().flatten().map(|n| n.to_uppercase()).collect();
+    sort_names(&mut names);
+
+    let mut blocks = Vec::new();
+    let mut outline = Outline::default();
+    let mut scene_index = 0;
+    for element in &fountain_doc.elements {
+        blocks.push(marks.marker([element]));
+        blocks.extend(outline.bookmark(element));
+        match element {
+            FarceElement::FSceneHeading(scene_heading) => {
+                let lines = heading_lines(scene_heading, scene_index, &casts[scene_index], profile);
+                blocks.push(Block::PageBreak);
+                blocks.push(Block::SceneHeading(lines));
+                scene_index += 1;
+            }
+            FarceElement::FAction(action) if !is_transition(&action.text) => {
+                let chars = styled_chars(&action.text);
+                // Characters who don't speak can still come and go
+                let mut action_names = capitalized_names(&chars);
+                action_names.extend(names.iter().cloned());
+                sort_names(&mut action_names);
+                let mut chars = to_uppercase(&chars);
+                underline_entrances(&mut chars, &action_names);
+                blocks.push(Block::Action {
+                    chars,
+                    is_centered: action.is_centered,
+                });
+            }
+            element => blocks.extend(Block::from_element(element, profile)),
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests;
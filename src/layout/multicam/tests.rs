@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::multicam::*;
+    use crate::layout::{paginate, Page, ScriptFormat};
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+
+    fn page_text(page: &Page) -> Vec<String> {
+        page.lines.iter().map(|l| l.text()).collect()
+    }
+
+    fn underlined(chars: &[StyledChar]) -> String {
+        chars
+            .iter()
+            .map(|(c, style, _)| if style.underline { *c } else { '.' })
+            .collect()
+    }
+
+    #[test]
+    fn test_scene_letter() {
+        assert_eq!(scene_letter(0), "A");
+        assert_eq!(scene_letter(25), "Z");
+        assert_eq!(scene_letter(26), "AA");
+        assert_eq!(scene_letter(28), "CC");
+    }
+
+    #[test]
+    fn test_underline_entrances() {
+        let names = vec![
+            "FRED JR".to_string(),
+            "WILMA".to_string(),
+            "FRED".to_string(),
+        ];
+        let mut chars = to_uppercase(&styled_chars("Fred and Wilma enter. Fred Jr exits."));
+        underline_entrances(&mut chars, &names);
+        assert_eq!(underlined(&chars), "FRED AND WILMA ENTER..FRED JR EXITS.");
+
+        // Not an entrance
+        let mut chars = to_uppercase(&styled_chars("FRED ENTERTAINS. ALFRED ENTERS."));
+        underline_entrances(&mut chars, &names);
+        assert_eq!(underlined(&chars), ".".repeat(chars.len()));
+    }
+
+    #[test]
+    fn test_capitalized_names() {
+        let chars = styled_chars("Fred waits. THE WAITER and BETTY, HIS BOSS ENTER.");
+        assert_eq!(
+            capitalized_names(&chars),
+            vec!["THE WAITER", "BETTY", "HIS BOSS"]
+        );
+    }
+
+    #[test]
+    fn test_multicam_pagination() {
+        let input = "Title: Test
+
+INT. KITCHEN - DAY
+
+Wilma is cooking. Fred enters.
+
+FRED
+Hello.
+
+WILMA
+Hi.
+
+INT. LIVING ROOM - NIGHT
+
+Barney sits. THE MAILMAN ENTERS, then exits.
+
+BARNEY
+Hey.
+";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Multicam, &LayoutProfile::multicam());
+        assert_eq!(pages.len(), 2);
+        let first = page_text(&pages[0]);
+        assert_eq!(first[0].trim(), "SCENE A");
+        assert_eq!(first[2], "INT. KITCHEN - DAY");
+        assert_eq!(first[3], "(FRED, WILMA)");
+        assert_eq!(first[5], "WILMA IS COOKING. FRED ENTERS.");
+        assert!(pages[0].lines[5].spans[1].style.underline);
+        let second = page_text(&pages[1]);
+        assert_eq!(second[0].trim(), "SCENE B");
+        assert_eq!(second[3], "(BARNEY)");
+        // The mailman doesn't speak, but he's introduced in capitals
+        assert_eq!(second[5], "BARNEY SITS. THE MAILMAN ENTERS, THEN EXITS.");
+        let underlined: String = pages[1].lines[5]
+            .spans
+            .iter()
+            .filter(|span| span.style.underline)
+            .map(|span| span.text.clone())
+            .collect();
+        assert_eq!(underlined, "THE MAILMAN ENTERS");
+    }
+}
@@ -0,0 +1,90 @@
+// Radio / audio drama conventions. Every speech and sound cue is numbered,
+// with the character name (or "SFX:" / "MUSIC:") on the left and the speech
+// or cue beside it. Sound cues come from action that starts with "SFX:" or
+// "MUSIC:", and are printed in underlined capitals. Any other action is a
+// direction, which the layout profile puts in line with the speeches.
+
+use super::{
+    added_chars, cue_chars, numbered_row, spaced_lines, speech_chars, styled_chars, trim,
+    wrap_dialogue, wrapped_lines, Block, CueNumbering, Marks, Outline, Page, StyledChar,
+};
+use crate::document::{FarceDocument, FarceElement};
+use crate::profile::LayoutProfile;
+
+const CUE_PREFIXES: [&str; 2] = ["SFX:", "MUSIC:"];
+
+/// Split a sound cue into its prefix and the rest of the text, eg.
+/// "SFX: Door slams." gives ("SFX:", "Door slams.")
+fn split_cue(chars: &[StyledChar]) -> Option<(&[StyledChar], &[StyledChar])> {
+    let chars = trim(chars);
+    let text: String = chars.iter().map(|(c, _, _)| c).collect();
+    CUE_PREFIXES
+        .iter()
+        .find(|prefix| text.starts_with(*prefix))
+        .map(|prefix| chars.split_at(prefix.chars().count()))
+        .map(|(prefix, rest)| (prefix, trim(rest)))
+}
+
+fn sound_cue_chars(chars: &[StyledChar]) -> Vec<StyledChar> {
+    chars
+        .iter()
+        .flat_map(|&(c, mut style, source)| {
+            style.underline = true;
+            c.to_uppercase().map(move |c| (c, style, source))
+        })
+        .collect()
+}
+
+pub(super) fn blocks(
+    fountain_doc: &FarceDocument,
+    marks: Marks,
+    numbering: CueNumbering,
+    profile: &LayoutProfile,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut outline = Outline::default();
+    let mut number = 0;
+    for element in &fountain_doc.elements {
+        blocks.push(marks.marker([element]));
+        blocks.extend(outline.bookmark(element));
+        match element {
+            FarceElement::FDialogue(dialogue) => {
+                number += 1;
+                let mut label = cue_chars(dialogue);
+                label.extend(added_chars(":"));
+                let chars = speech_chars(dialogue);
+                let text = spaced_lines(&chars, wrap_dialogue(&chars, profile));
+                blocks.push(numbered_row(&label, text, number, profile));
+            }
+            FarceElement::FAction(action) => match split_cue(&styled_chars(&action.text)) {
+                Some((prefix, cue)) => {
+                    number += 1;
+                    let text = wrapped_lines(&sound_cue_chars(cue), profile.dialogue);
+                    blocks.push(numbered_row(prefix, text, number, profile));
+                }
+                None => blocks.extend(Block::from_element(element, profile)),
+            },
+            FarceElement::FSceneHeading(_) => {
+                if numbering == CueNumbering::PerScene {
+                    number = 0;
+                }
+                blocks.extend(Block::from_element(element, profile));
+            }
+            element => blocks.extend(Block::from_element(element, profile)),
+        }
+    }
+    blocks
+}
+
+/// Renumber the speeches and cues so the numbers start again on each page
+pub(super) fn renumber_per_page(pages: &mut [Page]) {
+    for page in pages {
+        let numbers = page.lines.iter_mut().filter_map(|l| l.number.as_mut());
+        for (i, number) in numbers.enumerate() {
+            *number = i + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::{paginate, CueNumbering, Page, ScriptFormat};
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+
+    const INPUT: &str = "Title: Test
+
+INT. KITCHEN - NIGHT
+
+SFX: Rain against the window.
+
+MARY
+Is that you, John?
+
+JOHN
+(off)
+Who else?
+
+A door creaks open.
+
+INT. HALL - NIGHT
+
+MUSIC: Sinister strings
+
+MARY
+John?
+";
+
+    fn numbers(page: &Page) -> Vec<usize> {
+        page.lines.iter().filter_map(|l| l.number).collect()
+    }
+
+    #[test]
+    fn test_radio_layout() {
+        let (_, fdoc) = parse_fountain(INPUT).unwrap();
+        let format = ScriptFormat::from_name("radio").unwrap();
+        let pages = paginate(&fdoc, format, &LayoutProfile::radio());
+        assert_eq!(pages.len(), 1);
+        let text: Vec<String> = pages[0]
+            .lines
+            .iter()
+            .map(|l| format!("{}{}", " ".repeat(l.indent), l.text()))
+            .collect();
+        assert_eq!(
+            text[..12],
+            [
+                "INT. KITCHEN - NIGHT",
+                "",
+                "    SFX:            RAIN AGAINST THE WINDOW.",
+                "",
+                "    MARY:           Is that you, John?",
+                "",
+                "    JOHN:             (off)",
+                "                    Who else?",
+                "",
+                "                    A door creaks open.",
+                "",
+                "INT. HALL - NIGHT",
+            ]
+        );
+        // The cue itself is underlined, but not its label
+        let cue = &pages[0].lines[2];
+        assert!(!cue.spans[0].style.underline);
+        assert!(cue.spans.last().unwrap().style.underline);
+        assert_eq!(numbers(&pages[0]), vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_radio_numbering() {
+        let (_, fdoc) = parse_fountain(INPUT).unwrap();
+        let profile = LayoutProfile {
+            lines_per_page: 10,
+            ..LayoutProfile::radio()
+        };
+        let per_page = ScriptFormat::Radio {
+            numbering: CueNumbering::PerPage,
+        };
+        let pages = paginate(&fdoc, per_page, &profile);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(numbers(&pages[0]), vec![1, 2, 3]);
+        assert_eq!(numbers(&pages[1]), vec![1, 2]);
+
+        let per_scene = ScriptFormat::Radio {
+            numbering: CueNumbering::PerScene,
+        };
+        let pages = paginate(&fdoc, per_scene, &profile);
+        assert_eq!(numbers(&pages[0]), vec![1, 2, 3]);
+        assert_eq!(numbers(&pages[1]), vec![1, 2]);
+
+        let mut input = INPUT.replace("INT. HALL - NIGHT\n\n", "");
+        input.push_str("\nJOHN\nYes?\n");
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, per_scene, &profile);
+        let numbers: Vec<usize> = pages.iter().flat_map(numbers).collect();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5, 6]);
+    }
+}
@@ -0,0 +1,112 @@
+// Sides: just the pages of the script that an actor needs for a casting
+// session or a shoot day. The pages keep their numbers from the full script,
+// and anything on them that isn't in one of the chosen scenes is left off.
+
+use super::{Line, Page};
+use std::collections::BTreeSet;
+
+/// Which scenes go in the sides, and whose dialogue gets highlighted
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sides {
+    pub scenes: Option<Vec<usize>>, // None for every scene the character speaks in
+    pub character: Option<String>,
+}
+
+impl Sides {
+    /// Parse a list of scene numbers, eg. "3, 5-7"
+    pub fn parse_scenes(text: &str) -> Result<Vec<usize>, String> {
+        // Scenes are numbered from 1
+        let number = |n: &str| match n.trim().parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number),
+            _ => Err(format!(
+                "Invalid scene number \"{}\" in \"{}\"",
+                n.trim(),
+                text
+            )),
+        };
+        let mut scenes = Vec::new();
+        for part in text.split(',') {
+            match part.split_once('-') {
+                Some((first, last)) => match (number(first)?, number(last)?) {
+                    (first, last) if first > last => return Err(format!(
+                        "Invalid scene range \"{}\" in \"{}\", the first scene is after the last",
+                        part.trim(),
+                        text
+                    )),
+                    (first, last) => scenes.extend(first..=last),
+                },
+                None => scenes.push(number(part)?),
+            }
+        }
+        Ok(scenes)
+    }
+
+    /// Just the chosen scenes' pages, with everything else on them blanked
+    /// out. The pages need to have been laid out with the character's
+    /// dialogue highlighted, if the scenes are the ones they speak in.
+    pub fn select(&self, pages: Vec<Page>) -> Vec<Page> {
+        let line_scenes = line_scenes(&pages);
+        let scenes: BTreeSet<usize> = match &self.scenes {
+            Some(scenes) => scenes.iter().copied().collect(),
+            None => pages
+                .iter()
+                .zip(&line_scenes)
+                .flat_map(|(page, scenes)| page.lines.iter().zip(scenes))
+                .filter(|(line, _)| line.highlighted)
+                .filter_map(|(_, scene)| *scene)
+                .collect(),
+        };
+        let is_chosen = |scene: &Option<usize>| scene.is_some_and(|s| scenes.contains(&s));
+        pages
+            .into_iter()
+            .zip(line_scenes)
+            .enumerate()
+            .filter(|(_, (_, line_scenes))| line_scenes.iter().any(is_chosen))
+            .map(|(i, (page, line_scenes))| {
+                let number = Some(page.display_number(i));
+                let lines = page
+                    .lines
+                    .into_iter()
+                    .zip(&line_scenes)
+                    .map(|(line, scene)| match is_chosen(scene) {
+                        true => line,
+                        false => Line::blank(),
+                    })
+                    .collect();
+                let bookmarks = page
+                    .bookmarks
+                    .into_iter()
+                    .filter(|b| is_chosen(&line_scenes[b.line.min(line_scenes.len() - 1)]))
+                    .collect();
+                Page {
+                    lines,
+                    bookmarks,
+                    number,
+                }
+            })
+            .collect()
+    }
+}
+
+/// The scene that each line on each page is in
+fn line_scenes(pages: &[Page]) -> Vec<Vec<Option<usize>>> {
+    let mut scene = None;
+    pages
+        .iter()
+        .map(|page| {
+            (0..page.lines.len())
+                .map(|i| {
+                    if let Some(next) = page
+                        .bookmarks
+                        .iter()
+                        .filter(|b| b.line == i)
+                        .find_map(|b| b.scene)
+                    {
+                        scene = Some(next);
+                    }
+                    scene
+                })
+                .collect()
+        })
+        .collect()
+}
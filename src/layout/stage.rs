@@ -0,0 +1,105 @@
+// Stage play conventions. Acts and scenes come from "#" and "##" sections,
+// with each act starting on a new page. Stage directions go in brackets. The
+// layout profile takes care of indenting them, and of centering the
+// character names.
+
+use super::{
+    added_chars, centered_lines, is_transition, styled_chars, trim, wrapped_lines, Block, Line,
+    Marks, Outline, Page, StyledChar, TextStyle, HEADER_LINES,
+};
+use crate::document::{FarceDocument, FarceElement, Section};
+use crate::profile::{ElementLayout, LayoutProfile};
+
+/// "# Act One" becomes a bold, underlined "ACT ONE", and "## Scene 2" an
+/// underlined "SCENE 2". Any deeper sections don't get printed.
+fn section_lines(section: &Section, profile: &LayoutProfile) -> Option<Vec<Line>> {
+    let style = match section.depth {
+        1 => TextStyle {
+            bold: true,
+            underline: true,
+            ..TextStyle::default()
+        },
+        2 => TextStyle {
+            underline: true,
+            ..TextStyle::default()
+        },
+        _ => return None,
+    };
+    let chars: Vec<StyledChar> = section
+        .text
+        .chars()
+        .enumerate()
+        .flat_map(|(i, c)| c.to_uppercase().map(move |u| (u, style, Some(i))))
+        .collect();
+    let centered = ElementLayout {
+        centered: true,
+        ..profile.scene_heading
+    };
+    Some(wrapped_lines(&chars, centered))
+}
+
+/// The dramatis personae, ie. everyone who speaks, on as many pages as it
+/// takes
+pub(super) fn cast_pages(fountain_doc: &FarceDocument, profile: &LayoutProfile) -> Vec<Page> {
+    let names = fountain_doc.get_character_names();
+    if names.is_empty() {
+        return Vec::new();
+    }
+    let mut lines = vec![Line::blank(); 4];
+    lines.extend(centered_lines("**DRAMATIS PERSONAE**", profile));
+    lines.extend([Line::blank(), Line::blank()]);
+    for name in names {
+        lines.extend(centered_lines(name, profile));
+    }
+    // No header, so the whole page is free
+    lines
+        .chunks(HEADER_LINES + profile.lines_per_page)
+        .map(|lines| Page {
+            lines: lines.to_vec(),
+            ..Page::default()
+        })
+        .collect()
+}
+
+fn stage_direction(chars: &[StyledChar]) -> Vec<StyledChar> {
+    let chars = trim(chars);
+    match chars.first() {
+        Some(('(', _, _)) => chars.to_vec(),
+        _ => [added_chars("("), chars.to_vec(), added_chars(")")].concat(),
+    }
+}
+
+pub(super) fn blocks(
+    fountain_doc: &FarceDocument,
+    marks: Marks,
+    profile: &LayoutProfile,
+) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut outline = Outline::default();
+    for element in &fountain_doc.elements {
+        // Each act starts on a new page
+        if matches!(element, FarceElement::FSection(section) if section.depth == 1) {
+            blocks.push(Block::PageBreak);
+        }
+        blocks.push(marks.marker([element]));
+        blocks.extend(outline.bookmark(element));
+        match element {
+            FarceElement::FSection(section) => {
+                blocks.extend(section_lines(section, profile).map(Block::SceneHeading));
+            }
+            FarceElement::FAction(action)
+                if !action.is_centered && !is_transition(&action.text) =>
+            {
+                blocks.push(Block::Action {
+                    chars: stage_direction(&styled_chars(&action.text)),
+                    is_centered: false,
+                });
+            }
+            element => blocks.extend(Block::from_element(element, profile)),
+        }
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::{paginate, Page, ScriptFormat};
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+
+    fn page_text(page: &Page) -> Vec<String> {
+        page.lines.iter().map(|l| l.text()).collect()
+    }
+
+    #[test]
+    fn test_stage_pagination() {
+        let input = "Title: Test
+
+# Act One
+
+## Scene 1
+
+A kitchen. Wilma is cooking.
+
+FRED
+Hello.
+(beat)
+Anyone home?
+
+# Act Two
+
+(Later.)
+
+WILMA
+Hi.
+";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let profile = LayoutProfile::stage();
+        let pages = paginate(&fdoc, ScriptFormat::Stage, &profile);
+        assert_eq!(pages.len(), 2);
+
+        let first = page_text(&pages[0]);
+        assert_eq!(first[0].trim(), "ACT ONE");
+        assert!(pages[0].lines[0].spans[0].style.bold);
+        assert_eq!(first[2].trim(), "SCENE 1");
+        assert_eq!(first[4], "(A kitchen. Wilma is cooking.)");
+        assert_eq!(pages[0].lines[4].indent, profile.action.indent);
+        assert_eq!(first[6], "FRED");
+        assert_eq!(pages[0].lines[6].indent, 28); // Centered in 60 columns
+        assert_eq!(first[7], "Hello.");
+        assert_eq!(first[8], "(beat)");
+
+        let second = page_text(&pages[1]);
+        assert_eq!(second[0].trim(), "ACT TWO");
+        assert_eq!(second[2], "(Later.)");
+    }
+}
@@ -0,0 +1,662 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::*;
+    use crate::parser::parse_fountain;
+    use crate::profile::{ElementLayout, LayoutProfile};
+
+    fn profile(lines_per_page: usize) -> LayoutProfile {
+        LayoutProfile {
+            lines_per_page,
+            ..LayoutProfile::feature()
+        }
+    }
+
+    fn page_text(page: &Page) -> Vec<String> {
+        page.lines.iter().map(|l| l.text()).collect()
+    }
+
+    #[test]
+    fn test_wrap() {
+        let chars = styled_chars("The quick brown fox jumps  over the lazy dog");
+        let lines: Vec<String> = wrap(&chars, 15)
+            .into_iter()
+            .map(|r| chars[r].iter().map(|(c, _, _)| c).collect())
+            .collect();
+        assert_eq!(
+            lines,
+            vec!["The quick brown", "fox jumps  over", "the lazy dog"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_keeps_styles() {
+        let chars = styled_chars("Some _underlined text_ here");
+        let element = ElementLayout {
+            indent: 0,
+            width: 15,
+            line_spacing: 1,
+            centered: false,
+        };
+        let lines = wrapped_lines(&chars, element);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans[1].text, "underlined");
+        assert!(lines[0].spans[1].style.underline);
+        assert_eq!(lines[1].spans[0].text, "text");
+        assert!(lines[1].spans[0].style.underline);
+    }
+
+    #[test]
+    fn test_sentence_boundaries() {
+        let chars = styled_chars("Hello. \"Is it me?\" You're looking for.");
+        let boundaries = sentence_boundaries(&chars);
+        assert_eq!(boundaries, vec![6, 18]);
+    }
+
+    #[test]
+    fn test_dialogue_breaks_between_sentences() {
+        let mut input = String::from("Title: Test\n\n");
+        for _ in 0..7 {
+            input.push_str("Someone does something that takes a line.\n\n");
+        }
+        input.push_str("FRED\n");
+        for _ in 0..4 {
+            input.push_str("This is a sentence that is long enough to wrap. ");
+        }
+        input.push('\n');
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(20));
+        assert_eq!(pages.len(), 2);
+
+        let first = page_text(&pages[0]);
+        assert_eq!(first[first.len() - 1], MORE);
+        assert!(first[first.len() - 2].ends_with("wrap."));
+        assert!(first.len() <= 20);
+
+        let second = page_text(&pages[1]);
+        assert_eq!(second[0], "FRED (CONT'D)");
+        assert!(second[1].starts_with("This is a sentence"));
+    }
+
+    #[test]
+    fn test_dialogue_moves_to_next_page_if_it_cant_break() {
+        let mut input = String::from("Title: Test\n\n");
+        for _ in 0..8 {
+            input.push_str("Someone does something that takes a line.\n\n");
+        }
+        input.push_str(
+            "FRED\nOne long sentence that can't be broken up, no matter how hard we try\n",
+        );
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(18));
+        assert_eq!(pages.len(), 2);
+        assert_eq!(page_text(&pages[1])[0], "FRED");
+    }
+
+    #[test]
+    fn test_tiny_pages() {
+        // Pages too small for the rules still have to put something on
+        // every page, even if it overflows
+        let input = "Title: Test\n\nFRED\nHello.\n\nBARNEY\nHi. How are you doing today, Fred? I haven't seen you for ages.\n";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        for lines_per_page in 1..=2 {
+            let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(lines_per_page));
+            let text: Vec<String> = pages.iter().flat_map(page_text).collect();
+            assert!(pages.iter().all(|page| !page.lines.is_empty()));
+            assert!(text.contains(&"Hello.".to_string()));
+            assert!(text.iter().any(|line| line.ends_with("for ages.")));
+        }
+    }
+
+    fn filler(lines: usize) -> String {
+        let mut input = String::from("Title: Test\n\n");
+        for _ in 0..lines {
+            input.push_str("Someone does something that takes a line.\n\n");
+        }
+        input
+    }
+
+    #[test]
+    fn test_scene_heading_keeps_with_next() {
+        // Room for the heading and one line of action, but not two
+        let mut input = filler(8);
+        input.push_str("INT. A HOUSE - DAY\n\n");
+        input.push_str(
+            "A lot happens here. It goes on for some time, and takes up a couple of lines.\n",
+        );
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(19));
+        assert_eq!(pages.len(), 2);
+        assert_eq!(page_text(&pages[1])[0], "INT. A HOUSE - DAY");
+    }
+
+    #[test]
+    fn test_cue_keeps_with_dialogue() {
+        let mut input = filler(9);
+        input.push_str("FRED\nHello.\n");
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(19));
+        assert_eq!(pages.len(), 2);
+        assert_eq!(page_text(&pages[1]), vec!["FRED", "Hello."]);
+    }
+
+    #[test]
+    fn test_action_breaks_between_sentences() {
+        let mut input = filler(5);
+        for _ in 0..4 {
+            input.push_str("This is a sentence of action that is long enough to wrap. ");
+        }
+        input.push('\n');
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(13));
+        assert_eq!(pages.len(), 2);
+        let first = page_text(&pages[0]);
+        assert_eq!(first.len(), 12);
+        assert!(first[11].ends_with("wrap."));
+        assert_eq!(page_text(&pages[1]).len(), 2);
+    }
+
+    #[test]
+    fn test_action_orphan_control() {
+        // There's room for one more line of action, but that would leave it
+        // on its own at the bottom of the page.
+        let mut input = filler(6);
+        input.push_str(
+            "Short sentence. Then a longer sentence, which goes on long enough to wrap.\n",
+        );
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(13));
+        assert_eq!(pages.len(), 2);
+        assert_eq!(page_text(&pages[0]).len(), 11);
+        assert!(page_text(&pages[1])[0].starts_with("Short sentence."));
+    }
+
+    #[test]
+    fn test_double_spaced_dialogue() {
+        let mut input = filler(5);
+        input.push_str("FRED\n");
+        for _ in 0..3 {
+            input.push_str("This is a sentence that is long enough to wrap. ");
+        }
+        input.push('\n');
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let profile = LayoutProfile {
+            lines_per_page: 17,
+            ..LayoutProfile::multicam()
+        };
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile);
+        assert_eq!(pages.len(), 2);
+        let first = page_text(&pages[0]);
+        assert_eq!(first[10], "FRED");
+        assert!(first[11].starts_with("This is a sentence"));
+        assert_eq!(first[12], "");
+        assert_eq!(first[first.len() - 1], MORE);
+        assert_eq!(page_text(&pages[1])[0], "FRED (CONT'D)");
+    }
+
+    #[test]
+    fn test_front_pages() {
+        let input = "Title: _Brick & Steel_\nAuthor: Stu Maschwitz\n\nFRED\nHello.\n";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let profile = LayoutProfile::feature();
+        let pages = front_pages(&fdoc, ScriptFormat::Screenplay, &profile);
+        assert_eq!(pages.len(), 1);
+        let text = page_text(&pages[0]);
+        assert_eq!(text[10], "Brick & Steel");
+        assert_eq!(text[12], "by");
+        assert_eq!(text[14], "Stu Maschwitz");
+        // Centered over the action, and underlined
+        let title = &pages[0].lines[10];
+        assert_eq!(title.indent, (60 - 13) / 2);
+        assert!(title.spans[0].style.underline);
+
+        // Stage plays get a list of the characters too
+        let pages = front_pages(&fdoc, ScriptFormat::Stage, &LayoutProfile::stage());
+        assert_eq!(pages.len(), 2);
+        assert_eq!(page_text(&pages[1])[4].trim(), "DRAMATIS PERSONAE");
+        assert_eq!(page_text(&pages[1])[7].trim(), "FRED");
+
+        let (_, fdoc) = parse_fountain("FRED\nHello.\n").unwrap();
+        assert!(front_pages(&fdoc, ScriptFormat::Screenplay, &profile).is_empty());
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut input = filler(0);
+        input.push_str("# Act One\n\nINT. A HOUSE - DAY\n\n");
+        input.push_str(&"Someone does something that takes a line.\n\n".repeat(9));
+        input.push_str("EXT. A GARDEN - NIGHT\n\nA fox.\n\n## Later\n\n");
+        input.push_str("INT. A SHED - NIGHT\n\nDust.\n");
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile(19));
+        assert_eq!(pages.len(), 2);
+        let bookmark = |depth, title: &str, line, scene| Bookmark {
+            depth,
+            title: title.to_string(),
+            line,
+            scene,
+        };
+        assert_eq!(
+            pages[0].bookmarks,
+            vec![
+                bookmark(1, "Act One", 0, None),
+                bookmark(2, "1 INT. A HOUSE - DAY", 0, Some(1)),
+            ]
+        );
+        // The heading moves to the next page, and the bookmark goes with it
+        assert_eq!(
+            pages[1].bookmarks,
+            vec![
+                bookmark(2, "2 EXT. A GARDEN - NIGHT", 0, Some(2)),
+                bookmark(2, "Later", 4, None),
+                bookmark(3, "3 INT. A SHED - NIGHT", 4, Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_placements() {
+        let mut input = filler(0);
+        input.push_str("# Act One\n\nINT. A HOUSE - DAY\n\n");
+        input.push_str(&"Someone does something that takes a line.\n\n".repeat(6));
+        input.push_str("FRED\n");
+        input.push_str(&"This is a sentence that is long enough to wrap. ".repeat(4));
+        input.push_str("\n\nEXT. A GARDEN - NIGHT\n\nA fox.\n");
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let layout = lay_out(
+            &fdoc,
+            ScriptFormat::Screenplay,
+            &profile(20),
+            PaginateOptions::default(),
+        );
+        assert_eq!(layout.placements.len(), fdoc.elements.len());
+        let placement = |page, line, lines| Placement { page, line, lines };
+        // Sections aren't printed
+        assert_eq!(layout.placements[0], vec![]);
+        assert_eq!(layout.placements[1], vec![placement(0, 0, 1)]);
+        assert_eq!(layout.placements[2], vec![placement(0, 2, 1)]);
+        // The dialogue breaks across the pages
+        let dialogue = &layout.placements[8];
+        assert_eq!(dialogue.len(), 2);
+        assert_eq!(dialogue[0].page, 0);
+        assert_eq!(dialogue[1].page, 1);
+        assert_eq!(dialogue[1].line, 0);
+        let garden = layout.placements[9][0];
+        assert_eq!(
+            page_text(&layout.pages[1])[garden.line],
+            "EXT. A GARDEN - NIGHT"
+        );
+
+        let position = |page, line| LinePosition { page, line };
+        let fox = layout.placements[10][0];
+        assert_eq!(
+            layout.scenes(),
+            vec![
+                SceneSpan {
+                    scene: 1,
+                    first: position(0, 0),
+                    last: position(1, dialogue[1].line + dialogue[1].lines - 1),
+                },
+                SceneSpan {
+                    scene: 2,
+                    first: position(1, garden.line),
+                    last: position(1, fox.line),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_revised_lines() {
+        let earlier = "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox sits quietly in the corner of the dusty room, watching the door.\n";
+        let current = "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox sits quietly in the corner of the dusty room, watching the window.\n\nA dog.\n";
+        let (_, earlier) = parse_fountain(earlier).unwrap();
+        let (_, current) = parse_fountain(current).unwrap();
+        let revision = crate::revision::compare(&earlier, current);
+        let pages = paginate_with(
+            &revision.document,
+            ScriptFormat::Screenplay,
+            &profile(50),
+            PaginateOptions {
+                changes: &revision.changes,
+                ..PaginateOptions::default()
+            },
+        );
+        let revised: Vec<(String, bool)> = pages[0]
+            .lines
+            .iter()
+            .map(|l| (l.text(), l.revised))
+            .filter(|(text, _)| !text.is_empty())
+            .collect();
+        assert_eq!(
+            revised,
+            vec![
+                (String::from("INT. A HOUSE - DAY"), false),
+                (
+                    String::from("A fox sits quietly in the corner of the dusty room, watching"),
+                    false
+                ),
+                (String::from("the window."), true),
+                (String::from("A dog."), true),
+            ]
+        );
+    }
+
+    fn revised_lines(
+        earlier: &str,
+        current: &str,
+        format: ScriptFormat,
+        profile: &LayoutProfile,
+    ) -> Vec<(String, bool)> {
+        let (_, earlier) = parse_fountain(&format!("Title: Test\n\n{}", earlier)).unwrap();
+        let (_, current) = parse_fountain(&format!("Title: Test\n\n{}", current)).unwrap();
+        let revision = crate::revision::compare(&earlier, current);
+        let options = PaginateOptions {
+            changes: &revision.changes,
+            ..PaginateOptions::default()
+        };
+        paginate_with(&revision.document, format, profile, options)
+            .iter()
+            .flat_map(|page| &page.lines)
+            .filter(|line| !line.spans.is_empty())
+            .map(|line| (line.text(), line.revised))
+            .collect()
+    }
+
+    #[test]
+    fn test_revised_generated_lines() {
+        // The "(MORE)" and "(CONT'D)" don't get marked, and a line that's
+        // changed to something that's elsewhere in the earlier version does
+        let lines = revised_lines(
+            "MARY\nFirst line.\nSecond line.\nThird line.\nFourth line.\n",
+            "MARY\nFirst line.\nSecond line.\nFirst line.\nFourth row.\n",
+            ScriptFormat::Screenplay,
+            &profile(4),
+        );
+        let line = |text: &str, revised| (String::from(text), revised);
+        assert_eq!(
+            lines,
+            vec![
+                line("MARY", false),
+                line("First line.", false),
+                line("Second line.", false),
+                line(MORE, false),
+                line("MARY (CONT'D)", false),
+                line("First line.", true),
+                line("Fourth row.", true),
+            ]
+        );
+
+        // Nor do the brackets round a stage direction
+        let lines = revised_lines(
+            "He sits.\nShe stands.\n",
+            "He sits.\nShe leaves.\n",
+            ScriptFormat::Stage,
+            &LayoutProfile::stage(),
+        );
+        assert_eq!(
+            lines,
+            vec![line("(He sits.", false), line("She leaves.)", true)]
+        );
+
+        // Nor does the label on a radio speech
+        let lines = revised_lines(
+            "MARY\nHello there.\nGoodbye.\n",
+            "MARY\nHello there.\nSee you.\n",
+            ScriptFormat::Radio {
+                numbering: CueNumbering::PerPage,
+            },
+            &LayoutProfile::radio(),
+        );
+        let revised: Vec<bool> = lines.iter().map(|(_, revised)| *revised).collect();
+        assert_eq!(revised, vec![false, true]);
+        assert!(lines[0].0.starts_with("MARY:"));
+    }
+
+    fn numbered_lines(numbers: impl Iterator<Item = usize>) -> String {
+        let mut input = String::from("Title: Test\n\nINT. A HOUSE - DAY\n\n");
+        for i in numbers {
+            input.push_str(&format!("Line {}.\n\n", i));
+        }
+        input
+    }
+
+    fn page_numbers(input: &str, lock: Option<&PageLock>) -> Vec<(String, String)> {
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let pages = paginate_with(
+            &fdoc,
+            ScriptFormat::Screenplay,
+            &profile(10),
+            PaginateOptions {
+                lock,
+                ..PaginateOptions::default()
+            },
+        );
+        pages
+            .iter()
+            .enumerate()
+            .map(|(i, page)| (page.display_number(i), page.lines[0].text()))
+            .collect()
+    }
+
+    fn lock_script(input: &str) -> PageLock {
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let layout = lay_out(
+            &fdoc,
+            ScriptFormat::Screenplay,
+            &profile(10),
+            PaginateOptions::default(),
+        );
+        PageLock::from_layout(&layout, &fdoc)
+    }
+
+    fn numbered(pages: &[(&str, &str)]) -> Vec<(String, String)> {
+        pages
+            .iter()
+            .map(|(number, line)| (number.to_string(), line.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_next_page_number() {
+        assert_eq!(lock::next_page_number("12"), "12A");
+        assert_eq!(lock::next_page_number("12A"), "12B");
+        assert_eq!(lock::next_page_number("12Z"), "12ZA");
+    }
+
+    #[test]
+    fn test_locked_pages() {
+        let lock = lock_script(&numbered_lines(1..=12));
+        let first_lines: Vec<&str> = lock.pages.iter().map(|p| p.first_line.as_str()).collect();
+        assert_eq!(
+            first_lines,
+            vec!["INT. A HOUSE - DAY", "Line 5.", "Line 10."]
+        );
+        let anchors: Vec<(Option<usize>, usize)> =
+            lock.pages.iter().map(|p| (p.element, p.line)).collect();
+        assert_eq!(anchors, vec![(Some(0), 0), (Some(5), 0), (Some(10), 0)]);
+        assert_eq!(
+            PageLock::from_toml(&toml::to_string(&lock).unwrap()),
+            Ok(lock.clone())
+        );
+
+        // Added lines push what doesn't fit onto an A page
+        let added = numbered_lines(1..=12).replace("Line 3.", "New A.\n\nNew B.\n\nLine 3.");
+        assert_eq!(
+            page_numbers(&added, Some(&lock)),
+            numbered(&[
+                ("1", "INT. A HOUSE - DAY"),
+                ("1A", "Line 3."),
+                ("2", "Line 5."),
+                ("3", "Line 10."),
+            ])
+        );
+
+        // A page that's all been cut is kept as part of the one before it
+        let cut = numbered_lines((1..=4).chain(10..=12));
+        assert_eq!(
+            page_numbers(&cut, Some(&lock)),
+            numbered(&[("1-2", "INT. A HOUSE - DAY"), ("3", "Line 10.")])
+        );
+    }
+
+    #[test]
+    fn test_locked_page_with_repeated_cue() {
+        // Page 2 starts with JOHN's second speech, not his first
+        let mut input = numbered_lines(1..=2);
+        input.push_str("JOHN\nHello.\n\nLine 3.\n\nJOHN\nGoodbye.\n\nLine 4.\n");
+        let lock = lock_script(&input);
+        let unlocked = page_numbers(&input, None);
+        assert_eq!(unlocked[1], (String::from("2"), String::from("JOHN")));
+        assert_eq!(lock.pages[1].first_line, "JOHN");
+        assert_eq!(lock.pages[1].element, Some(5));
+        assert_eq!(page_numbers(&input, Some(&lock)), unlocked);
+
+        // Still true with a line cut from the first page
+        let cut = input.replace("Line 1.\n\n", "");
+        assert_eq!(
+            page_numbers(&cut, Some(&lock)),
+            numbered(&[("1", "INT. A HOUSE - DAY"), ("2", "JOHN")])
+        );
+        let (_, fdoc) = parse_fountain(&cut).unwrap();
+        let pages = paginate_with(
+            &fdoc,
+            ScriptFormat::Screenplay,
+            &profile(10),
+            PaginateOptions {
+                lock: Some(&lock),
+                ..PaginateOptions::default()
+            },
+        );
+        assert_eq!(page_text(&pages[1])[1], "Goodbye.");
+    }
+
+    #[test]
+    fn test_locked_page_starting_with_contd() {
+        let speech = "JOHN\nOne. Two.\nThree. Four.\nFive. Six.\nSeven. Eight.\n";
+        let input = format!("{}{}", numbered_lines(1..=2), speech);
+        let unlocked = page_numbers(&input, None);
+        assert_eq!(
+            unlocked[1],
+            (String::from("2"), String::from("JOHN (CONT'D)"))
+        );
+        let lock = lock_script(&input);
+        assert_eq!(lock.pages[1].first_line, "JOHN");
+        assert_eq!((lock.pages[1].element, lock.pages[1].line), (Some(3), 4));
+
+        // With a line cut, the whole speech would fit on page 1, but page 2
+        // still starts in the middle of it
+        let cut = format!("{}{}", numbered_lines(1..=1), speech);
+        assert_eq!(page_numbers(&cut, None).len(), 1);
+        assert_eq!(
+            page_numbers(&cut, Some(&lock)),
+            numbered(&[("1", "INT. A HOUSE - DAY"), ("2", "JOHN (CONT'D)")])
+        );
+    }
+
+    #[test]
+    fn test_parse_scenes() {
+        assert_eq!(Sides::parse_scenes("3, 5-7"), Ok(vec![3, 5, 6, 7]));
+        assert!(Sides::parse_scenes("3,x").is_err());
+        assert_eq!(Sides::parse_scenes("5-5"), Ok(vec![5]));
+        // A range the wrong way round
+        assert!(Sides::parse_scenes("5-3").is_err());
+        // There's no scene 0
+        assert!(Sides::parse_scenes("0").is_err());
+        assert!(Sides::parse_scenes("0-2").is_err());
+    }
+
+    #[test]
+    fn test_sides() {
+        let mut input = filler(0);
+        for (scene, speaker) in [
+            ("INT. A HOUSE - DAY", "MARY"),
+            ("EXT. A GARDEN - NIGHT", "FRED"),
+            ("INT. A SHED - NIGHT", "MARY"),
+        ] {
+            input.push_str(&format!("{}\n\n", scene));
+            input.push_str(&"Someone does something that takes a line.\n\n".repeat(3));
+            input.push_str(&format!("{}\nHello.\n\n", speaker));
+        }
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let options = PaginateOptions {
+            highlight: Some("Mary"),
+            ..PaginateOptions::default()
+        };
+        let pages = paginate_with(&fdoc, ScriptFormat::Screenplay, &profile(19), options);
+        assert_eq!(pages.len(), 2);
+        let highlighted: Vec<String> = pages[0]
+            .lines
+            .iter()
+            .filter(|l| l.highlighted)
+            .map(|l| l.text())
+            .collect();
+        assert_eq!(highlighted, vec!["MARY", "Hello."]);
+
+        // Mary's scenes are 1 and 3, which are on both pages, with scene 2
+        // left off
+        let sides = Sides {
+            scenes: None,
+            character: Some(String::from("Mary")),
+        };
+        let selected = sides.select(paginate_with(
+            &fdoc,
+            ScriptFormat::Screenplay,
+            &profile(19),
+            options,
+        ));
+        assert_eq!(selected.len(), 2);
+        assert!(page_text(&selected[0]).contains(&String::from("INT. A HOUSE - DAY")));
+        assert!(!page_text(&selected[0]).contains(&String::from("EXT. A GARDEN - NIGHT")));
+        assert_eq!(selected[1].number, Some(String::from("2")));
+
+        // Just scene 1, which is on the first page
+        let sides = Sides {
+            scenes: Some(vec![1]),
+            character: None,
+        };
+        let selected = sides.select(paginate(&fdoc, ScriptFormat::Screenplay, &profile(19)));
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].number, Some(String::from("1")));
+    }
+
+    #[test]
+    fn test_big_fish_pagination() {
+        // With the shipped feature profile. Checking the page of each scene
+        // heading needs the Final Draft PDF from fountain.io, which isn't in
+        // the repo; samples/Big-Fish.pdf is our own older output.
+        let input = include_str!("../../samples/Big-Fish.fountain");
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let profile = LayoutProfile::feature();
+        let pages = paginate(&fdoc, ScriptFormat::Screenplay, &profile);
+        for (i, page) in pages.iter().enumerate() {
+            assert!(page.lines.len() <= profile.lines_per_page);
+            let last_line = page.lines.last().unwrap().text();
+            assert!(!last_line.starts_with("INT. "));
+            assert!(!last_line.starts_with("EXT. "));
+            if last_line == MORE {
+                assert!(pages[i + 1].lines[0].text().ends_with("(CONT'D)"));
+            }
+        }
+        // Every scene heading is there, in order
+        let headings: Vec<String> = fdoc
+            .elements
+            .iter()
+            .filter_map(|e| match e {
+                FarceElement::FSceneHeading(heading) => Some(heading.heading_text()),
+                _ => None,
+            })
+            .collect();
+        let lines: Vec<String> = pages
+            .iter()
+            .flat_map(|page| &page.lines)
+            .map(|line| line.text())
+            .collect();
+        let mut next_line = 0;
+        for heading in &headings {
+            let found = lines[next_line..]
+                .iter()
+                .position(|line| heading.starts_with(line.as_str()) && !line.is_empty());
+            next_line += found.expect(heading) + 1;
+        }
+    }
+}
@@ -2,8 +2,12 @@ pub mod constants;
 pub mod document;
 pub mod html;
 pub mod inline_parser;
+pub mod layout;
 pub mod parser;
 pub mod pdf;
+pub mod profile;
+pub mod revision;
+pub mod text;
 pub mod utils;
 
 use std::io;
@@ -35,19 +39,27 @@ impl io::Write for MyWriter {
 }
 
 pub fn fountain_to_pdf(input: &str, paper_size: pdf::PaperSize) -> Vec<u8> {
+    let options = pdf::PdfOptions {
+        paper_size: Some(paper_size),
+        ..pdf::PdfOptions::default()
+    };
+    fountain_to_pdf_with_options(input, &options)
+}
+
+/// Like `fountain_to_pdf`, but with all the options, eg. to make a
+/// reproducible PDF
+pub fn fountain_to_pdf_with_options(input: &str, options: &pdf::PdfOptions) -> Vec<u8> {
     let (_remainder, fdoc) = parser::parse_fountain(input).expect("Could not parse fountain doc");
-    let genpdf_doc = pdf::create_pdf(fdoc, paper_size).expect("Could not create pdf");
+    let pdf = pdf::create_pdf(fdoc, options).expect("Could not create pdf");
     let mut writer = MyWriter::new();
-    genpdf_doc
-        .render(&mut writer)
-        .expect("Failed to render pdf");
+    pdf.render(&mut writer).expect("Failed to render pdf");
     writer.into_inner()
 }
 
 pub fn fountain_to_html(input: &str) -> String {
     let (_remainder, fdoc) = parser::parse_fountain(input).expect("Could not parse fountain doc");
     let mut writer = MyWriter::new();
-    html::write_html(fdoc, &mut writer, false).expect("Failed to write html");
+    html::write_html(fdoc, &mut writer, false, None).expect("Failed to write html");
     let bytes = writer.into_inner();
     String::from_utf8(bytes).expect("Could not decode html as utf8")
 }
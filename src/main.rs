@@ -2,19 +2,29 @@ mod constants;
 mod document;
 mod html;
 mod inline_parser;
+mod layout;
 mod parser;
 mod pdf;
+mod profile;
+mod revision;
 mod stats;
+mod text;
 mod utils;
 
-use crate::html::write_html;
+use crate::document::FarceDocument;
+use crate::html::{write_av_html, write_html};
+use crate::layout::{CueNumbering, PageLock, ScriptFormat, Sides};
 use crate::pdf::create_pdf;
-use crate::stats::print_stats;
+use crate::profile::LayoutProfile;
+use crate::stats::{
+    get_character_stats, print_stats, scene_reports, write_scene_csv, write_scene_json,
+};
+use crate::text::write_text;
 
 use std::env;
 use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
 #[derive(Copy, Clone, Debug)]
@@ -22,6 +32,9 @@ enum OutputMode {
     Html,
     Pdf,
     Stats,
+    Text,
+    Csv,
+    Json,
 }
 
 impl OutputMode {
@@ -30,6 +43,9 @@ impl OutputMode {
             OutputMode::Html => Some(String::from("html")),
             OutputMode::Pdf => Some(String::from("pdf")),
             OutputMode::Stats => None,
+            OutputMode::Text => Some(String::from("txt")),
+            OutputMode::Csv => Some(String::from("csv")),
+            OutputMode::Json => Some(String::from("json")),
         }
     }
 }
@@ -45,6 +61,15 @@ impl fmt::Display for OutputMode {
             OutputMode::Stats => {
                 write!(f, "Show stats")
             }
+            OutputMode::Text => {
+                write!(f, "Write plain text file")
+            }
+            OutputMode::Csv => {
+                write!(f, "Write CSV scene report")
+            }
+            OutputMode::Json => {
+                write!(f, "Write JSON scene report")
+            }
         }
     }
 }
@@ -58,6 +83,9 @@ fn print_usage() {
     println!("             --pdf     Write PDF file (default)");
     println!("            --html     Write HTML file");
     println!("           --stats     Show screenplay stats");
+    println!("            --text     Write plain text file, laid out as in the PDF");
+    println!("             --csv     Write each scene's pages and length in eighths");
+    println!("            --json     of a page, as CSV or JSON");
     println!();
     println!("              --a4     A4 page size (default)");
     println!("                -a");
@@ -65,8 +93,96 @@ fn print_usage() {
     println!("          --letter     US Letter page size");
     println!("                -l");
     println!();
+    println!("     --paper size      Page size: A4, Letter, Legal, A5, B5, or a width");
+    println!("                       and height, eg. 6x9in or 150x230mm (default is");
+    println!("                       the title page's Paper, then the profile's, then");
+    println!("                       A4). The text stays the standard size, centered");
+    println!("                       on bigger paper and scaled down on smaller paper");
+    println!();
+    println!("            --2-up     Two pages side by side on each landscape sheet");
+    println!("         --booklet     Landscape sheets in booklet order, to print");
+    println!("                       double sided, fold and staple. Blank pages are");
+    println!("                       added to make a multiple of 4");
+    println!("      --crop-marks     Crop and fold marks around the pages, with");
+    println!("                       --2-up or --booklet");
+    println!();
     println!(" --output filename     Choose output filename (default is the input");
-    println!("       -o filename     filename but with .pdf or .html extension)");
+    println!("       -o filename     filename but with .pdf, .html, .txt, .csv or");
+    println!("                       .json extension)");
+    println!();
+    println!("    --format name      Script format: screenplay (default), multicam,");
+    println!("                       stage, av (two column audio / visual), radio or");
+    println!("                       comic. Only screenplay and av affect HTML");
+    println!("                       output");
+    println!();
+    println!("--cue-numbering page   Whether the numbers on a radio script's speeches");
+    println!("--cue-numbering scene  and sound cues start again on each page (default)");
+    println!("                       or each scene");
+    println!();
+    println!("   --profile name      Page layout: feature, multicam, stage, av, radio,");
+    println!("   --profile file      comic, or a TOML file with your own layout");
+    println!("                       (default depends on the format)");
+    println!();
+    println!("       --font file     Font file to use instead of Courier Prime");
+    println!("  --bold-font file     Bold, italic and bold italic font files. Any");
+    println!("--italic-font file     that aren't given use the --font file.");
+    println!("--bold-italic-font file");
+    println!();
+    println!("--fallback-font path   Font file, or directory of font files, to use for");
+    println!("                       characters the main font doesn't have. Can be");
+    println!("                       given more than once.");
+    println!();
+    println!("       --date date     Creation date for the PDF's metadata, eg.");
+    println!("                       2023-04-01 (default is the title page's Draft");
+    println!("                       date, or else now)");
+    println!("  --watermark text     Print the text (eg. a name, or CONFIDENTIAL)");
+    println!("                       across every page of the PDF, instead of the");
+    println!("                       title page's Watermark");
+    println!("  --watermark-batch file");
+    println!("                       Make a copy of the PDF for each name in the file");
+    println!("                       (one per line), watermarked with that name, eg.");
+    println!("                       \"Script - Jane Doe.pdf\" for -o Script.pdf");
+    println!();
+    println!("--previous-draft file  Compare with an earlier draft, and put an");
+    println!("                       asterisk in the margin of the PDF next to every");
+    println!("                       line that's changed. Cut scenes are kept as");
+    println!("                       OMITTED, so the scene numbers stay the same.");
+    println!("--revision-color color The revision's colour, eg. Blue, for the header");
+    println!("                       (default is the title page's Revision). Unless");
+    println!("                       there's a --header, the header becomes");
+    println!("                       \"{{revision}} Rev. {{draft_date}}||{{page}}.\"");
+    println!();
+    println!("     --highlight name  Give the character's cues and dialogue a");
+    println!("                       background tint, in PDF or HTML output");
+    println!("   --highlight-batch   Make a copy for each speaking character, with");
+    println!("                       their dialogue highlighted, eg.");
+    println!("                       \"Script - MARY.pdf\" for -o Script.pdf");
+    println!();
+    println!("       --sides scenes  Just print the pages with these scenes on them,");
+    println!("                       eg. 3,5-7, leaving everything else off, for an");
+    println!("                       actor's sides. The pages keep their numbers.");
+    println!("--sides-character name Highlight the character's dialogue in the");
+    println!("                       sides. Without --sides, the sides are every");
+    println!("                       scene they speak in.");
+    println!();
+    println!("  --save-page-lock file");
+    println!("                       Lock the script's page numbers, by saving where");
+    println!("                       its page breaks are in the file");
+    println!("       --page-lock file");
+    println!("                       Keep the page breaks saved in the file. Anything");
+    println!("                       that's been added goes on A pages, eg. 12A, 12B");
+    println!();
+    println!("    --reproducible     Make the same script give an identical PDF every");
+    println!("                       time. The date comes from --date, then");
+    println!("                       SOURCE_DATE_EPOCH, then the Draft date.");
+    println!();
+    println!("   --header template   What to print at the top and bottom of each");
+    println!("   --footer template   page, as left|center|right, eg. \"{{title}}||{{page}}.\"");
+    println!("                       Templates can use {{title}}, {{draft_date}},");
+    println!("                       {{revision}} (the title page's Revision), {{scenes}}");
+    println!("                       and {{page}}. Anything with {{page}} in it is left");
+    println!("                       off page 1. The default is a page number");
+    println!("                       header, \"||{{page}}.\", and no footer.");
     println!();
     println!("            --help     Show this help");
     println!();
@@ -80,6 +196,58 @@ fn print_usage() {
     exit(1)
 }
 
+/// The file name for someone's copy of the output, eg. "Script - Jane
+/// Doe.pdf" for "Script.pdf"
+fn copy_path(output: &Path, name: &str) -> PathBuf {
+    let stem = output.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let extension = output.extension().and_then(|s| s.to_str()).unwrap_or("pdf");
+    let file_name: String = format!("{} - {}.{}", stem, name, extension)
+        .chars()
+        .map(|c| if std::path::is_separator(c) { '_' } else { c })
+        .collect();
+    output.with_file_name(file_name)
+}
+
+/// Read the names for a batch of watermarked PDFs, and work out each one's
+/// file name
+fn watermarked_copies(names_file: &Path, output: &Path) -> Vec<(String, PathBuf)> {
+    let names = match fs::read_to_string(names_file) {
+        Ok(names) => names,
+        Err(err) => {
+            eprintln!("Couldn't read {} ({})", names_file.display(), err);
+            exit(1)
+        }
+    };
+    let copies: Vec<(String, PathBuf)> = names
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| (name.to_string(), copy_path(output, name)))
+        .collect();
+    if copies.is_empty() {
+        eprintln!("There aren't any names in {}", names_file.display());
+        exit(1)
+    }
+    copies
+}
+
+/// A copy for each speaking character, with their dialogue highlighted
+fn highlighted_copies(document: &FarceDocument, output: &Path) -> Vec<(Option<String>, PathBuf)> {
+    let mut names: Vec<String> = get_character_stats(document).into_keys().collect();
+    if names.is_empty() {
+        eprintln!("There aren't any speaking characters to highlight");
+        exit(1)
+    }
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| {
+            let path = copy_path(output, &name);
+            (Some(name), path)
+        })
+        .collect()
+}
+
 fn main() {
     let mut args = env::args().skip(1);
     let mut maybe_input_filename: Option<&str> = None;
@@ -88,9 +256,30 @@ fn main() {
     let output_filename: Option<&str>;
     let mut positional_args = Vec::new();
     let mut requested_paper_sizes = Vec::new();
-    let paper_size: pdf::PaperSize;
+    let paper_size: Option<pdf::PaperSize>;
     let mut requested_output_modes = Vec::new();
     let output_mode: OutputMode;
+    let mut font_files: [Option<PathBuf>; 4] = Default::default(); // regular, bold, italic, bold italic
+    let mut fallback_fonts = Vec::new();
+    let mut format = ScriptFormat::Screenplay;
+    let mut maybe_profile: Option<LayoutProfile> = None;
+    let mut maybe_cue_numbering: Option<CueNumbering> = None;
+    let mut timestamp: Option<pdf::Timestamp> = None;
+    let mut reproducible = false;
+    let mut maybe_header: Option<pdf::HeaderFooter> = None;
+    let mut footer = pdf::HeaderFooter::default();
+    let mut watermark: Option<String> = None;
+    let mut watermark_batch: Option<PathBuf> = None;
+    let mut previous_draft: Option<PathBuf> = None;
+    let mut revision_color: Option<String> = None;
+    let mut page_lock: Option<PageLock> = None;
+    let mut save_page_lock: Option<PathBuf> = None;
+    let mut sides_scenes: Option<Vec<usize>> = None;
+    let mut sides_character: Option<String> = None;
+    let mut highlight: Option<String> = None;
+    let mut highlight_batch = false;
+    let mut requested_impositions = Vec::new();
+    let mut crop_marks = false;
 
     while let Some(arg) = args.next() {
         match &arg[..] {
@@ -103,6 +292,15 @@ fn main() {
             "--stats" => {
                 requested_output_modes.push(OutputMode::Stats);
             }
+            "--text" => {
+                requested_output_modes.push(OutputMode::Text);
+            }
+            "--csv" => {
+                requested_output_modes.push(OutputMode::Csv);
+            }
+            "--json" => {
+                requested_output_modes.push(OutputMode::Json);
+            }
             "--output" | "-o" => {
                 maybe_output_filename = args.next();
                 if maybe_output_filename.is_none() {
@@ -110,12 +308,198 @@ fn main() {
                     print_usage()
                 }
             }
+            "--font" | "--bold-font" | "--italic-font" | "--bold-italic-font" => {
+                let index = match &arg[..] {
+                    "--font" => 0,
+                    "--bold-font" => 1,
+                    "--italic-font" => 2,
+                    _ => 3,
+                };
+                match args.next() {
+                    Some(path) => font_files[index] = Some(PathBuf::from(path)),
+                    None => {
+                        eprintln!("No value specified for parameter {}", &arg);
+                        print_usage()
+                    }
+                }
+            }
+            "--format" => match args.next() {
+                Some(name) => match ScriptFormat::from_name(&name) {
+                    Some(f) => format = f,
+                    None => {
+                        eprintln!("Unrecognized format {}", name);
+                        print_usage()
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--cue-numbering" => match args.next() {
+                Some(name) => match CueNumbering::from_name(&name) {
+                    Some(numbering) => maybe_cue_numbering = Some(numbering),
+                    None => {
+                        eprintln!("Unrecognized cue numbering {}", name);
+                        print_usage()
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--profile" => match args.next() {
+                Some(name) => {
+                    maybe_profile = Some(match LayoutProfile::builtin(&name) {
+                        Some(builtin) => builtin,
+                        None => match LayoutProfile::from_toml_file(Path::new(&name)) {
+                            Ok(from_file) => from_file,
+                            Err(e) => {
+                                eprintln!("{}", e);
+                                exit(1)
+                            }
+                        },
+                    })
+                }
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--date" => match args.next() {
+                Some(date) => match pdf::Timestamp::parse(&date) {
+                    Some(parsed) => timestamp = Some(parsed),
+                    None => {
+                        eprintln!("Couldn't understand the date {}", date);
+                        print_usage()
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--fallback-font" => match args.next() {
+                Some(path) => fallback_fonts.push(PathBuf::from(path)),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
             "--a4" | "-a" => {
                 requested_paper_sizes.push(pdf::PaperSize::A4);
             }
             "--letter" | "-l" => {
                 requested_paper_sizes.push(pdf::PaperSize::Letter);
             }
+            "--paper" => match args.next() {
+                Some(name) => match pdf::PaperSize::from_name(&name) {
+                    Ok(size) => requested_paper_sizes.push(size),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        print_usage()
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--2-up" => requested_impositions.push(pdf::Imposition::TwoUp),
+            "--booklet" => requested_impositions.push(pdf::Imposition::Booklet),
+            "--crop-marks" => crop_marks = true,
+            "--header" | "--footer" => match args.next() {
+                Some(template) => match pdf::HeaderFooter::parse(&template) {
+                    Ok(parsed) if arg == "--header" => maybe_header = Some(parsed),
+                    Ok(parsed) => footer = parsed,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        print_usage()
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--watermark" => match args.next() {
+                Some(text) => watermark = Some(text),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--watermark-batch" => match args.next() {
+                Some(path) => watermark_batch = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--previous-draft" => match args.next() {
+                Some(path) => previous_draft = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--revision-color" => match args.next() {
+                Some(color) => revision_color = Some(color),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--page-lock" => match args.next() {
+                Some(path) => match PageLock::from_toml_file(Path::new(&path)) {
+                    Ok(lock) => page_lock = Some(lock),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        exit(1)
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--highlight" => match args.next() {
+                Some(name) => highlight = Some(name),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--highlight-batch" => highlight_batch = true,
+            "--sides" => match args.next() {
+                Some(scenes) => match Sides::parse_scenes(&scenes) {
+                    Ok(scenes) => sides_scenes = Some(scenes),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        exit(1)
+                    }
+                },
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--sides-character" => match args.next() {
+                Some(name) => sides_character = Some(name),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--save-page-lock" => match args.next() {
+                Some(path) => save_page_lock = Some(PathBuf::from(path)),
+                None => {
+                    eprintln!("No value specified for parameter {}", &arg);
+                    print_usage()
+                }
+            },
+            "--reproducible" => reproducible = true,
             "--help" => print_usage(),
             _ => {
                 if arg.starts_with('-') {
@@ -143,6 +527,21 @@ fn main() {
     }
     input_filename = maybe_input_filename.unwrap();
 
+    if let Some(numbering) = maybe_cue_numbering {
+        match format {
+            ScriptFormat::Radio { .. } => format = ScriptFormat::Radio { numbering },
+            _ => {
+                eprintln!("--cue-numbering only works with --format radio");
+                print_usage()
+            }
+        }
+    }
+    let profile = maybe_profile.unwrap_or_else(|| format.default_profile());
+    let header = maybe_header.unwrap_or_else(|| match revision_color {
+        Some(_) => pdf::HeaderFooter::revision(),
+        None => pdf::HeaderFooter::page_number(),
+    });
+
     match requested_output_modes.len() {
         0 => {
             output_mode = OutputMode::Pdf;
@@ -151,14 +550,59 @@ fn main() {
             output_mode = requested_output_modes[0];
         }
         _ => {
-            eprintln!("Please choose only one of --pdf, --html and --stats");
+            eprintln!("Please choose only one of --pdf, --html, --stats, --text, --csv and --json");
             exit(1)
         }
     }
 
+    if watermark_batch.is_some() && !matches!(output_mode, OutputMode::Pdf) {
+        eprintln!("--watermark-batch only works with PDF output");
+        print_usage()
+    }
+    if (page_lock.is_some() || save_page_lock.is_some()) && !matches!(output_mode, OutputMode::Pdf)
+    {
+        eprintln!("--page-lock and --save-page-lock only work with PDF output");
+        print_usage()
+    }
+    if (highlight.is_some() || highlight_batch)
+        && !matches!(output_mode, OutputMode::Pdf | OutputMode::Html)
+    {
+        eprintln!("--highlight and --highlight-batch only work with PDF or HTML output");
+        print_usage()
+    }
+    if highlight_batch && watermark_batch.is_some() {
+        eprintln!("Please choose only one of --highlight-batch and --watermark-batch");
+        exit(1)
+    }
+    if requested_impositions.len() > 1 {
+        eprintln!("Please choose only one of --2-up and --booklet");
+        exit(1)
+    }
+    let imposition = requested_impositions.first().copied();
+    if crop_marks && imposition.is_none() {
+        eprintln!("--crop-marks only works with --2-up or --booklet");
+        print_usage()
+    }
+    if imposition.is_some() && !matches!(output_mode, OutputMode::Pdf) {
+        eprintln!("--2-up and --booklet only work with PDF output");
+        print_usage()
+    }
+    let sides = match (sides_scenes, sides_character) {
+        (None, None) => None,
+        (scenes, character) => Some(Sides { scenes, character }),
+    };
+    if sides.is_some() && !matches!(output_mode, OutputMode::Pdf) {
+        eprintln!("--sides and --sides-character only work with PDF output");
+        print_usage()
+    }
+
     let output_filename_string: String;
     output_filename = match output_mode {
-        OutputMode::Html | OutputMode::Pdf => match maybe_output_filename {
+        OutputMode::Html
+        | OutputMode::Pdf
+        | OutputMode::Text
+        | OutputMode::Csv
+        | OutputMode::Json => match maybe_output_filename {
             Some(ref of) => Some(of),
             None => {
                 let input_path = Path::new(input_filename);
@@ -173,10 +617,10 @@ fn main() {
 
     match requested_paper_sizes.len() {
         0 => {
-            paper_size = pdf::PaperSize::A4;
+            paper_size = None;
         }
         1 => {
-            paper_size = requested_paper_sizes[0];
+            paper_size = Some(requested_paper_sizes[0]);
         }
         _ => {
             eprintln!("Multiple page sizes requested");
@@ -192,7 +636,26 @@ fn main() {
         }
         None => {}
     }
-    println!("Page size: {}", paper_size);
+    if let Some(paper_size) = paper_size {
+        println!("Page size: {}", paper_size);
+    }
+
+    let [regular_font, bold_font, italic_font, bold_italic_font] = font_files;
+    let font = match regular_font {
+        Some(regular) => Some(pdf::FontFiles {
+            regular,
+            bold: bold_font,
+            italic: italic_font,
+            bold_italic: bold_italic_font,
+        }),
+        None => {
+            if bold_font.is_some() || italic_font.is_some() || bold_italic_font.is_some() {
+                eprintln!("--bold-font, --italic-font and --bold-italic-font need --font too");
+                print_usage()
+            }
+            None
+        }
+    };
 
     let input = match fs::read_to_string(input_filename) {
         Ok(s) => s,
@@ -202,30 +665,137 @@ fn main() {
         }
     };
 
+    let earlier_input = previous_draft.map(|path| match fs::read_to_string(&path) {
+        Ok(s) => s,
+        Err(err) => {
+            eprintln!("Couldn't read previous draft {} ({})", path.display(), err);
+            exit(1)
+        }
+    });
+    let earlier_draft = earlier_input
+        .as_deref()
+        .map(|input| match parser::parse_fountain(input) {
+            Ok((_remainder, document)) => document,
+            Err(e) => {
+                eprintln!("Couldn't parse the previous draft ({})", e);
+                exit(1)
+            }
+        });
+
     match parser::parse_fountain(&input) {
-        Ok((_remainder, document)) => match output_mode {
-            OutputMode::Pdf => match create_pdf(document, paper_size) {
-                Ok(genpdf_document) => {
-                    genpdf_document
-                        .render_to_file(output_filename.unwrap())
-                        .expect("Failed to write output file");
+        Ok((_remainder, document)) => {
+            // Each file to write, and whose dialogue gets highlighted in it
+            let copies = match (highlight_batch, output_filename) {
+                (true, Some(output)) => highlighted_copies(&document, Path::new(output)),
+                (false, Some(output)) => vec![(highlight, PathBuf::from(output))],
+                (_, None) => Vec::new(),
+            };
+            match output_mode {
+                OutputMode::Pdf => {
+                    let mut options = pdf::PdfOptions {
+                        paper_size,
+                        font,
+                        format,
+                        profile,
+                        fallback_fonts,
+                        timestamp,
+                        reproducible,
+                        header,
+                        footer,
+                        watermark,
+                        revision_color,
+                        page_lock,
+                        sides,
+                        highlight: None,
+                        imposition,
+                        crop_marks,
+                    };
+                    for (highlight, output) in copies {
+                        options.highlight = highlight;
+                        let pdf = match &earlier_draft {
+                            Some(earlier) => {
+                                pdf::create_revised_pdf(document.clone(), earlier, &options)
+                            }
+                            None => create_pdf(document.clone(), &options),
+                        };
+                        match pdf {
+                            Ok(pdf) => {
+                                if let Some(path) = &save_page_lock {
+                                    if let Err(e) = pdf.page_lock().to_toml_file(path) {
+                                        eprintln!("{}", e);
+                                        exit(1)
+                                    }
+                                }
+                                let result = match &watermark_batch {
+                                    Some(names_file) => {
+                                        pdf.render_batch(&watermarked_copies(names_file, &output))
+                                    }
+                                    None => pdf.render_to_file(&output),
+                                };
+                                if let Err(e) = result {
+                                    eprintln!("{}", e);
+                                    exit(1)
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("Couldn't generate PDF ({})", e);
+                                exit(1)
+                            }
+                        }
+                    }
                 }
-                Err(e) => {
-                    eprintln!("Couldn't generate PDF ({})", e);
-                    exit(1)
+                OutputMode::Html => {
+                    for (highlight, output) in copies {
+                        let f = fs::File::create(&output).expect(&format!(
+                            "Could not open file {} for writing",
+                            output.display()
+                        ));
+                        let highlight = highlight.as_deref();
+                        match format {
+                            ScriptFormat::Av => {
+                                write_av_html(document.clone(), f, true, highlight).unwrap()
+                            }
+                            _ => write_html(document.clone(), f, true, highlight).unwrap(),
+                        }
+                    }
+                }
+                OutputMode::Stats => {
+                    print_stats(&document, format, &profile);
+                }
+                OutputMode::Text => {
+                    let f = fs::File::create(output_filename.unwrap()).expect(&format!(
+                        "Could not open file {} for writing",
+                        output_filename.unwrap()
+                    ));
+                    if let Err(e) = write_text(&document, f, format, &profile) {
+                        eprintln!("{}", e);
+                        exit(1)
+                    }
+                }
+                OutputMode::Csv | OutputMode::Json => {
+                    let f = match fs::File::create(output_filename.unwrap()) {
+                        Ok(f) => f,
+                        Err(err) => {
+                            eprintln!(
+                                "Could not open file {} for writing ({})",
+                                output_filename.unwrap(),
+                                err
+                            );
+                            exit(1)
+                        }
+                    };
+                    let reports = scene_reports(&document, format, &profile);
+                    let result = match output_mode {
+                        OutputMode::Csv => write_scene_csv(&reports, f),
+                        _ => write_scene_json(&reports, f),
+                    };
+                    if let Err(e) = result {
+                        eprintln!("{}", e);
+                        exit(1)
+                    }
                 }
-            },
-            OutputMode::Html => {
-                let f = fs::File::create(output_filename.unwrap()).expect(&format!(
-                    "Could not open file {} for writing",
-                    output_filename.unwrap()
-                ));
-                write_html(document, f, true).unwrap();
-            }
-            OutputMode::Stats => {
-                print_stats(&document);
             }
-        },
+        }
         Err(error) => {
             println!("Parsing error: {:?}", error);
             exit(1)
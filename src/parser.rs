@@ -27,7 +27,7 @@ fn nonempty_line(input: &str) -> IResult<&str, &str> {
     if line.trim() == "" {
         return Err(nom::Err::Error(nom::error::Error::new(
             input,
-            nom::error::ErrorKind::Space
+            nom::error::ErrorKind::Space,
         )));
     }
     Ok((i, line))
@@ -79,7 +79,7 @@ fn parse_dialogue(input: &str) -> IResult<&str, FarceElement> {
     let e = FarceElement::FDialogue(Dialogue {
         character_name: String::from(character_name),
         character_extensions: extensions.iter().map(|s| s.to_string()).collect(),
-        text: String::from(lines.join(" ")),
+        text: lines.join("\n"),
     });
     Ok((remainder, e))
 }
@@ -115,6 +115,21 @@ fn parse_centered_action(input: &str) -> IResult<&str, FarceElement> {
     ))
 }
 
+fn parse_section(input: &str) -> IResult<&str, FarceElement> {
+    // Like "# Act One", or "## Scene 2" for a subsection
+    let (remainder, (hashes, text)) = pair(
+        take_while1(|c| c == '#'),
+        delimited(space0, not_line_ending, eol_or_eof),
+    )(input)?;
+    Ok((
+        remainder,
+        FarceElement::FSection(Section {
+            depth: hashes.len(),
+            text: text.trim().to_string(),
+        }),
+    ))
+}
+
 fn parse_page_break(input: &str) -> IResult<&str, FarceElement> {
     let (remainder, _) =
         terminated(take_while_m_n(3, 1e23 as usize, |s| s == '='), eol_or_eof)(input)?;
@@ -123,6 +138,7 @@ fn parse_page_break(input: &str) -> IResult<&str, FarceElement> {
 
 pub fn parse_element(input: &str) -> IResult<&str, FarceElement> {
     let (remainder, element) = alt((
+        parse_section,
         parse_scene_heading,
         parse_dialogue,
         parse_page_break,
@@ -140,7 +156,7 @@ pub fn parse_elements(input: &str) -> IResult<&str, Vec<FarceElement>> {
 fn parse_multiline_titlepage_field_key(input: &str) -> IResult<&str, &str> {
     // Matches the start of a multiline title page field, eg "Author:\n"
     terminated(
-        is_not(":"),
+        is_not(":\r\n"),
         terminated(tag(":"), terminated(space0, line_ending)),
     )(input)
 }
@@ -157,7 +173,7 @@ fn parse_multiline_titlepage_field(input: &str) -> IResult<&str, (String, String
 fn parse_simple_titlepage_field(input: &str) -> IResult<&str, (String, String)> {
     // Simple one-line field like "Author: John July"
     let (remainder, (key, value)) = terminated(
-        separated_pair(is_not(":"), tag(": "), not_line_ending),
+        separated_pair(is_not(":\r\n"), tag(": "), not_line_ending),
         line_ending,
     )(input)?;
     Ok((remainder, (key.to_string(), value.to_string())))
@@ -42,6 +42,7 @@ mod tests {
 
         if let FarceElement::FDialogue(d) = element {
             assert_eq!(d.character_name, "FRED");
+            assert_eq!(d.text, "Hello\nSailor");
         } else {
             assert!(false);
         }
@@ -110,6 +111,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_section() {
+        let (remainder, element) = parse_element("## Scene 2\n\nFRED\nHello\n").unwrap();
+        assert_eq!(remainder, "FRED\nHello\n");
+        match element {
+            FarceElement::FSection(section) => {
+                assert_eq!(section.depth, 2);
+                assert_eq!(section.text, "Scene 2");
+            }
+            _ => panic!(),
+        }
+    }
+
     #[test]
     fn test_parse_elements() {
         let result = parse_elements(ELEMENTS);
@@ -175,6 +189,15 @@ mod tests {
         assert_eq!(document.elements.len(), 5);
     }
 
+    #[test]
+    fn test_parse_document_with_colons() {
+        // "SFX: ..." further down isn't a title page field
+        let input = "Title: Test\n\nINT. HOUSE - DAY\n\nSFX: A door slams.\n";
+        let (_, document) = parse_fountain(input).unwrap();
+        assert_eq!(document.title_page.unwrap().fields.len(), 1);
+        assert_eq!(document.elements.len(), 2);
+    }
+
     #[test]
     fn test_parse_dialogue_multiple_extensions() {
         let (remainder, element) =
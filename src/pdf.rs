@@ -1,6 +1,10 @@
 use crate::constants;
-use crate::document::{FarceDocument, FarceElement};
-use crate::inline_parser::{parse_inline, Expression};
+use crate::document::FarceDocument;
+use crate::layout::{
+    self, Layout, Page, PageLock, PaginateOptions, ScriptFormat, Sides, TextStyle, HEADER_LINES,
+};
+use crate::profile::LayoutProfile;
+use crate::revision::{self, Change};
 use allsorts::{
     binary::read::ReadScope,
     font::read_cmap_subtable,
@@ -9,32 +13,283 @@ use allsorts::{
     tag,
 };
 use genpdf;
-use genpdf::elements::{Alignment, Paragraph};
+use genpdf::elements::Alignment;
 use genpdf::fonts::FontFamily;
-use genpdf::{elements, fonts, style, Element};
+use genpdf::{fonts, render, style, Element, Mm, Position, RenderResult};
 use include_dir::{include_dir, Dir};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+mod header;
+mod imposition;
+mod metadata;
+mod outline;
+mod watermark;
+
+pub use header::HeaderFooter;
+use header::PageDecorations;
+pub use imposition::Imposition;
+use metadata::Metadata;
+pub use metadata::Timestamp;
+use outline::OutlineEntry;
 
 static FONTS_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/fonts/truetype/Courier Prime");
 const SKIP_FONT_SUBSETTING: bool = false;
+const HIGHLIGHT_COLOR: style::Color = style::Color::Rgb(255, 240, 140);
+
+// The layout module's columns and lines are based on a monospaced font, with
+// glyphs 0.6em wide, so at 12pt there are 10 characters and 6 lines to the
+// inch. The page number sits at the top margin, and the text starts a few
+// lines below it.
+const CHAR_WIDTH_EM: f32 = 0.6;
 
 fn inches(inches: f32) -> f32 {
     // return mm
     inches * 25.4
 }
-#[derive(Copy, Clone, Debug)]
+
+fn points(points: f32) -> f32 {
+    // return mm
+    inches(points / 72.0)
+}
+
+fn char_width(profile: &LayoutProfile) -> f32 {
+    // return mm
+    points(profile.font_size as f32 * CHAR_WIDTH_EM)
+}
+
+fn line_height(profile: &LayoutProfile) -> f32 {
+    // return mm
+    points(profile.font_size as f32)
+}
+// The layout profiles' margins are for A4 and US Letter, so the text fits on
+// anything at least as wide as A4 and as tall as Letter, and it's centered on
+// anything bigger than both
+const MIN_PAPER_SIZE: (f32, f32) = (210.0, 279.0);
+const MAX_STANDARD_PAPER_SIZE: (f32, f32) = (216.0, 297.0);
+
+/// The paper the PDF is for. The text is always the same size: on paper
+/// that's too small for it, the pages are laid out on A4 and then scaled down.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "String")]
 pub enum PaperSize {
     A4,
     Letter,
+    Legal,
+    A5,
+    B5,
+    Custom { width: f32, height: f32 }, // In mm
 }
 impl PaperSize {
-    fn get_genpdf_paper_size(&self) -> genpdf::PaperSize {
-        match self {
-            PaperSize::A4 => genpdf::PaperSize::A4,
-            PaperSize::Letter => genpdf::PaperSize::Letter,
+    /// A paper size by name, eg. "A5", or its width and height in mm or
+    /// inches, eg. "150x230mm" or "6 x 9 in"
+    pub fn from_name(name: &str) -> Result<PaperSize, String> {
+        let error = || {
+            format!(
+                "Invalid paper size \"{}\" (use A4, Letter, Legal, A5, B5 or a width and height, eg. 6x9in or 150x230mm)",
+                name
+            )
+        };
+        let lower = name.trim().to_lowercase();
+        match lower.as_str() {
+            "a4" => return Ok(PaperSize::A4),
+            "letter" | "us letter" => return Ok(PaperSize::Letter),
+            "legal" | "us legal" => return Ok(PaperSize::Legal),
+            "a5" => return Ok(PaperSize::A5),
+            "b5" => return Ok(PaperSize::B5),
+            _ => {}
+        }
+        let (dimensions, mm_per_unit) = match lower.strip_suffix("mm") {
+            Some(dimensions) => (dimensions, 1.0),
+            None => match lower.strip_suffix("in") {
+                Some(dimensions) => (dimensions, inches(1.0)),
+                None => return Err(error()),
+            },
+        };
+        let (width, height) = dimensions.split_once(['x', '×']).ok_or_else(error)?;
+        let length = |n: &str| match n.trim().parse::<f32>() {
+            Ok(n) if n > 0.0 && n.is_finite() => Ok(n * mm_per_unit),
+            _ => Err(error()),
+        };
+        Ok(PaperSize::Custom {
+            width: length(width)?,
+            height: length(height)?,
+        })
+    }
+
+    /// Width and height in mm, rounded to the nearest mm for US sizes, as
+    /// genpdf has them
+    pub fn size(&self) -> (f32, f32) {
+        match *self {
+            PaperSize::A4 => (210.0, 297.0),
+            PaperSize::Letter => (216.0, 279.0),
+            PaperSize::Legal => (216.0, 356.0),
+            PaperSize::A5 => (148.0, 210.0),
+            PaperSize::B5 => (176.0, 250.0),
+            PaperSize::Custom { width, height } => (width, height),
+        }
+    }
+
+    /// Whether the text fits on the paper at its normal size
+    fn fits_text(&self) -> bool {
+        let (width, height) = self.size();
+        width >= MIN_PAPER_SIZE.0 && height >= MIN_PAPER_SIZE.1
+    }
+
+    /// The paper the pages get laid out on, before they're scaled down to
+    /// fit this paper if it's too small
+    fn layout_paper(&self) -> PaperSize {
+        match self.fits_text() {
+            true => *self,
+            false => PaperSize::A4,
+        }
+    }
+
+    /// How much further in the text goes from each side, and from the top and
+    /// bottom, to keep it in the middle of paper bigger than A4 and Letter.
+    /// In mm.
+    fn extra_margins(&self) -> (f32, f32) {
+        let (width, height) = self.layout_paper().size();
+        (
+            (width - MAX_STANDARD_PAPER_SIZE.0).max(0.0) / 2.0,
+            (height - MAX_STANDARD_PAPER_SIZE.1).max(0.0) / 2.0,
+        )
+    }
+
+    /// Check that the header, the profile's lines of text and the footer on
+    /// the line after them all fit between the top and bottom margins
+    fn check_text_fits(&self, profile: &LayoutProfile) -> Result<(), String> {
+        let (_, height) = self.layout_paper().size();
+        let (_, extra_height) = self.extra_margins();
+        let text_height = height
+            - 2.0 * extra_height
+            - inches(profile.margins.top)
+            - inches(profile.margins.bottom);
+        let max_lines = (text_height / line_height(profile)) as usize;
+        let lines = HEADER_LINES + profile.lines_per_page + 1; // The footer's after the text
+        match lines <= max_lines {
+            true => Ok(()),
+            false => Err(format!(
+                "The layout profile's {} lines per page don't fit on {} paper, with the header and footer (there's room for {})",
+                profile.lines_per_page,
+                self,
+                max_lines.saturating_sub(HEADER_LINES + 1)
+            )),
+        }
+    }
+
+    fn get_genpdf_paper_size(&self) -> genpdf::Size {
+        match self.layout_paper() {
+            PaperSize::A4 => genpdf::PaperSize::A4.into(),
+            PaperSize::Letter => genpdf::PaperSize::Letter.into(),
+            PaperSize::Legal => genpdf::PaperSize::Legal.into(),
+            paper => {
+                let (width, height) = paper.size();
+                genpdf::Size::new(width, height)
+            }
         }
     }
 }
+
+impl TryFrom<String> for PaperSize {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<PaperSize, String> {
+        PaperSize::from_name(&name)
+    }
+}
+/// Font files to use instead of the embedded Courier Prime. Any style that
+/// isn't given uses the regular font.
+#[derive(Clone, Debug)]
+pub struct FontFiles {
+    pub regular: PathBuf,
+    pub bold: Option<PathBuf>,
+    pub italic: Option<PathBuf>,
+    pub bold_italic: Option<PathBuf>,
+}
+
+impl FontFiles {
+    fn read(&self) -> Result<FontFamily<Vec<u8>>, String> {
+        let read = |path: &Path| {
+            fs::read(path)
+                .map_err(|e| format!("Couldn't read font file {} ({})", path.display(), e))
+        };
+        let regular = read(&self.regular)?;
+        let read_or_regular = |path: &Option<PathBuf>| match path {
+            Some(path) => read(path),
+            None => Ok(regular.clone()),
+        };
+        Ok(FontFamily {
+            bold: read_or_regular(&self.bold)?,
+            italic: read_or_regular(&self.italic)?,
+            bold_italic: read_or_regular(&self.bold_italic)?,
+            regular,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PdfOptions {
+    // None for the title page's Paper, or the profile's, or A4
+    pub paper_size: Option<PaperSize>,
+    // The main font, or None for Courier Prime
+    pub font: Option<FontFiles>,
+    pub format: ScriptFormat,
+    pub profile: LayoutProfile,
+    // Font files (or directories of them) to use for any characters that
+    // the main font doesn't have, in order of preference
+    pub fallback_fonts: Vec<PathBuf>,
+    // The creation date for the metadata, instead of the draft date
+    pub timestamp: Option<Timestamp>,
+    // Make the same script give a byte for byte identical PDF every time
+    pub reproducible: bool,
+    // Printed at the top and bottom of each script page
+    pub header: HeaderFooter,
+    pub footer: HeaderFooter,
+    // Printed across every page, instead of the title page's Watermark
+    pub watermark: Option<String>,
+    // The revision's colour (White, Blue, Pink etc.) for the header, instead
+    // of the title page's Revision
+    pub revision_color: Option<String>,
+    // The page breaks of the locked script, to keep its page numbers
+    pub page_lock: Option<PageLock>,
+    // Just print the pages with these scenes on them, for an actor's sides
+    pub sides: Option<Sides>,
+    // The character whose cues and dialogue get a background tint
+    pub highlight: Option<String>,
+    // Print the pages 2-up or as a booklet, instead of one to a sheet
+    pub imposition: Option<Imposition>,
+    // Crop marks around the imposed pages
+    pub crop_marks: bool,
+}
+
+impl Default for PdfOptions {
+    fn default() -> PdfOptions {
+        PdfOptions {
+            paper_size: None,
+            font: None,
+            format: ScriptFormat::Screenplay,
+            profile: LayoutProfile::default(),
+            fallback_fonts: Vec::new(),
+            timestamp: None,
+            reproducible: false,
+            header: HeaderFooter::page_number(),
+            footer: HeaderFooter::default(),
+            watermark: None,
+            revision_color: None,
+            page_lock: None,
+            sides: None,
+            highlight: None,
+            imposition: None,
+            crop_marks: false,
+        }
+    }
+}
+
 impl fmt::Display for PaperSize {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -44,18 +299,25 @@ impl fmt::Display for PaperSize {
             PaperSize::Letter => {
                 write!(f, "US Letter")
             }
+            PaperSize::Legal => {
+                write!(f, "US Legal")
+            }
+            PaperSize::A5 => {
+                write!(f, "A5")
+            }
+            PaperSize::B5 => {
+                write!(f, "B5")
+            }
+            PaperSize::Custom { width, height } => {
+                write!(f, "{}mm x {}mm", width, height)
+            }
         }
     }
 }
 
-struct TextState {
-    bold: bool,
-    italic: bool,
-    underline: bool,
-}
-
-impl TextState {
+impl TextStyle {
     fn get_genpdf_style(&self) -> style::Style {
+        // NB genpdf doesn't draw underlines itself, see draw_underline
         let mut style = style::Style::default();
         if self.bold {
             style.merge(style::Effect::Bold);
@@ -70,179 +332,730 @@ impl TextState {
     }
 }
 
-fn render_text_elements(
-    p: &mut Paragraph,
-    expressions: &Vec<Expression>,
-    text_state: &mut TextState,
-) {
-    for e in expressions {
-        match e {
-            Expression::Text(t) => {
-                p.push(style::StyledString {
-                    s: t.to_string(),
-                    style: text_state.get_genpdf_style(),
-                });
-            }
-            Expression::Bold(v) => {
-                text_state.bold = true;
-                render_text_elements(p, &v, text_state);
-                text_state.bold = false;
-            }
-            Expression::Italic(v) => {
-                text_state.italic = true;
-                render_text_elements(p, &v, text_state);
-                text_state.italic = false;
-            }
-            Expression::BoldItalic(v) => {
-                text_state.bold = true;
-                text_state.italic = true;
-                render_text_elements(p, &v, text_state);
-                text_state.bold = false;
-                text_state.italic = false;
+/// Which font to use for any characters that the main font doesn't have
+#[derive(Clone, Default)]
+struct FontFallbacks {
+    families: Vec<FontFamily<fonts::Font>>,
+    char_families: HashMap<char, usize>, // Index into families
+}
+
+impl FontFallbacks {
+    /// Split some text into runs that can each be printed in a single font.
+    /// Each run comes with the index of its fallback font, if it needs one.
+    fn split_runs<'a>(&self, text: &'a str) -> Vec<(&'a str, Option<usize>)> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut current = None;
+        for (i, c) in text.char_indices() {
+            let family = self.char_families.get(&c).copied();
+            if i > 0 && family != current {
+                runs.push((&text[start..i], current));
+                start = i;
             }
-            Expression::Underline(v) => {
-                // Not actually supported (yet?)
-                text_state.underline = true;
-                render_text_elements(p, &v, text_state);
-                text_state.underline = false;
+            current = family;
+        }
+        if start < text.len() {
+            runs.push((&text[start..], current));
+        }
+        runs
+    }
+
+    fn get_style(&self, style: style::Style, family: Option<usize>) -> style::Style {
+        match family {
+            Some(i) => style.with_font_family(self.families[i]),
+            None => style,
+        }
+    }
+
+    fn str_width(&self, font_cache: &fonts::FontCache, style: style::Style, text: &str) -> Mm {
+        let mut width = Mm::from(0);
+        for (run, family) in self.split_runs(text) {
+            width += self.get_style(style, family).str_width(font_cache, run);
+        }
+        width
+    }
+
+    /// Print some plain text, in whichever fonts it needs
+    fn print_str(
+        &self,
+        area: &render::Area<'_>,
+        font_cache: &fonts::FontCache,
+        position: Position,
+        style: style::Style,
+        text: &str,
+    ) -> Result<(), genpdf::error::Error> {
+        let mut x = position.x;
+        for (run, family) in self.split_runs(text) {
+            let run_style = self.get_style(style, family);
+            area.print_str(font_cache, Position::new(x, position.y), run_style, run)?;
+            x += run_style.str_width(font_cache, run);
+        }
+        Ok(())
+    }
+}
+
+/// The parts of a font's head, hhea and post tables we need to draw
+/// underlines, in font units
+#[derive(Copy, Clone, Debug)]
+struct FontMetrics {
+    units_per_em: f32,
+    ascent: f32,
+    descent: f32,
+    underline_position: f32,
+    underline_thickness: f32,
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Result<i16, String> {
+    data.get(offset..offset + 2)
+        .map(|b| i16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "Font table is too short".to_string())
+}
+
+fn get_font_metrics(data: &[u8]) -> Result<FontMetrics, String> {
+    let font_file = ReadScope::new(data)
+        .read::<allsorts::font_data::FontData<'_>>()
+        .map_err(|e| format!("Couldn't read font ({:?})", e))?;
+    let provider = font_file
+        .table_provider(0)
+        .map_err(|e| format!("Couldn't read font ({:?})", e))?;
+    let read_table = |table_tag, name| {
+        provider
+            .read_table_data(table_tag)
+            .map_err(|e| format!("Couldn't read font's {} table ({:?})", name, e))
+    };
+    let head = read_table(tag::HEAD, "head")?;
+    let hhea = read_table(tag::HHEA, "hhea")?;
+    let post = read_table(tag::POST, "post")?;
+    Ok(FontMetrics {
+        units_per_em: read_i16(&head, 18)? as u16 as f32,
+        ascent: read_i16(&hhea, 4)? as f32,
+        descent: read_i16(&hhea, 6)? as f32,
+        underline_position: read_i16(&post, 8)? as f32,
+        underline_thickness: read_i16(&post, 10)? as f32,
+    })
+}
+
+/// Draw the underline for some text printed at `position`, which (as with
+/// genpdf's `print_str`) is the top left corner of the text.
+fn draw_underline(
+    area: &render::Area<'_>,
+    position: Position,
+    width: Mm,
+    font_size: u8,
+    metrics: &FontMetrics,
+) {
+    let thickness = metrics.underline_thickness;
+    let font_unit = points(font_size as f32) / metrics.units_per_em;
+    // The underline position is the top of the line, relative to the baseline
+    let y = position.y
+        + Mm::from(font_unit * (metrics.ascent - metrics.underline_position + thickness / 2.0));
+    area.draw_line(
+        vec![
+            Position::new(position.x, y),
+            Position::new(position.x + width, y),
+        ],
+        style::LineStyle::new().with_thickness(font_unit * thickness),
+    );
+}
+
+/// Draw a highlighter pen stripe behind some text printed at `position`
+fn draw_highlight(area: &render::Area<'_>, position: Position, width: Mm, profile: &LayoutProfile) {
+    let height = Mm::from(line_height(profile));
+    let y = position.y + height / 2.0;
+    area.draw_line(
+        vec![
+            Position::new(position.x, y),
+            Position::new(position.x + width, y),
+        ],
+        style::LineStyle::new()
+            .with_thickness(height)
+            .with_color(HIGHLIGHT_COLOR),
+    );
+}
+
+/// The body of the screenplay, as already laid out by the layout module.
+/// Renders one page each time genpdf calls it, with every line placed on the
+/// grid rather than flowed.
+struct ScreenplayPages {
+    front_pages: Vec<layout::Page>, // The title page etc., without a header or footer
+    pages: Vec<layout::Page>,
+    next_page: usize,
+    fallbacks: FontFallbacks,
+    metrics: FontFamily<FontMetrics>,
+    profile: LayoutProfile,
+    decorations: PageDecorations,
+}
+
+impl ScreenplayPages {
+    /// Print a line at `y`, with its revision asterisk, highlight and cue
+    /// number if it has them
+    fn print_line(
+        &self,
+        area: &render::Area<'_>,
+        font_cache: &fonts::FontCache,
+        style: style::Style,
+        line: &layout::Line,
+        y: f32,
+    ) -> Result<(), genpdf::error::Error> {
+        let mut x = Mm::from(char_width(&self.profile) * line.indent as f32);
+        if line.revised {
+            // In the right margin
+            let x = char_width(&self.profile) * (self.profile.action.right_edge() + 2) as f32;
+            area.print_str(font_cache, Position::new(x, y), style, "*")?;
+        }
+        if line.highlighted && !line.spans.is_empty() {
+            let width = char_width(&self.profile) * line.text().chars().count() as f32;
+            draw_highlight(area, Position::new(x, y), Mm::from(width), &self.profile);
+        }
+        if let Some(number) = line.number {
+            let number = format!("{}.", number);
+            area.print_str(font_cache, Position::new(0, y), style, &number)?;
+        }
+        for span in &line.spans {
+            for (run, family) in self.fallbacks.split_runs(&span.text) {
+                let run_style = self
+                    .fallbacks
+                    .get_style(style.and(span.style.get_genpdf_style()), family);
+                let position = Position::new(x, y);
+                let width = run_style.str_width(font_cache, run);
+                area.print_str(font_cache, position, run_style, run)?;
+                if span.style.underline {
+                    let metrics = match (span.style.bold, span.style.italic) {
+                        (false, false) => &self.metrics.regular,
+                        (true, false) => &self.metrics.bold,
+                        (false, true) => &self.metrics.italic,
+                        (true, true) => &self.metrics.bold_italic,
+                    };
+                    draw_underline(area, position, width, style.font_size(), metrics);
+                }
+                x += width;
             }
         }
+        Ok(())
     }
 }
 
-fn render_inline_formatting(text: &str, is_centered: bool) -> Paragraph {
-    let mut p: Paragraph = Paragraph::default();
-    let mut_ref = &mut p;
-    match parse_inline(&text) {
-        Ok((_remainder, expressions)) => {
-            let mut text_state = TextState {
-                bold: false,
-                italic: false,
-                underline: false,
-            };
-            render_text_elements(mut_ref, &expressions, &mut text_state);
-            match is_centered {
-                true => p.aligned(Alignment::Center),
-                false => p,
+impl Element for ScreenplayPages {
+    fn render(
+        &mut self,
+        context: &genpdf::Context,
+        area: render::Area<'_>,
+        style: style::Style,
+    ) -> Result<RenderResult, genpdf::error::Error> {
+        let font_cache = &context.font_cache;
+        let num_pages = self.front_pages.len() + self.pages.len();
+        if let Some(page) = self.front_pages.get(self.next_page) {
+            for (i, line) in page.lines.iter().enumerate() {
+                let y = line_height(&self.profile) * i as f32;
+                self.print_line(&area, font_cache, style, line, y)?;
             }
+            self.next_page += 1;
+            return Ok(RenderResult {
+                size: area.size(),
+                has_more: self.next_page < num_pages,
+            });
         }
-        Err(e) => {
-            p.push(format!("{}", e));
-            p
+        let index = self.next_page - self.front_pages.len();
+        let page = &self.pages[index];
+        // The header goes at the top margin and the footer on the line after
+        // the last line of text, lined up with the edges of the action
+        let right_edge =
+            Mm::from(char_width(&self.profile) * self.profile.action.right_edge() as f32);
+        let footer_y = Mm::from(
+            line_height(&self.profile) * (HEADER_LINES + self.profile.lines_per_page) as f32,
+        );
+        let decorations = [
+            (Mm::from(0), self.decorations.header(index)),
+            (footer_y, self.decorations.footer(index)),
+        ];
+        for (y, parts) in decorations {
+            for (alignment, text) in parts {
+                let width = self.fallbacks.str_width(font_cache, style, &text);
+                let x = match alignment {
+                    Alignment::Left => Mm::from(0),
+                    Alignment::Center => (right_edge - width) / 2.0,
+                    Alignment::Right => right_edge - width,
+                };
+                self.fallbacks
+                    .print_str(&area, font_cache, Position::new(x, y), style, &text)?;
+            }
+        }
+        for (i, line) in page.lines.iter().enumerate() {
+            let y = line_height(&self.profile) * (HEADER_LINES + i) as f32;
+            self.print_line(&area, font_cache, style, line, y)?;
+        }
+        self.next_page += 1;
+        Ok(RenderResult {
+            size: area.size(),
+            has_more: self.next_page < num_pages,
+        })
+    }
+}
+
+fn get_embedded_font(font_filename: &str) -> Vec<u8> {
+    FONTS_DIR
+        .get_file(font_filename)
+        .expect("Couldn't open font file")
+        .contents()
+        .to_vec()
+}
+
+fn is_font_file(path: &Path) -> bool {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => ["ttf", "otf"].contains(&extension.to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Read the given font files, and any font files in the given directories
+fn read_font_files(paths: &[PathBuf]) -> Result<Vec<Vec<u8>>, String> {
+    let mut font_files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let entries = fs::read_dir(path)
+                .map_err(|e| format!("Couldn't read font directory {} ({})", path.display(), e))?;
+            let mut dir_files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|p| is_font_file(p))
+                .collect();
+            dir_files.sort();
+            font_files.extend(dir_files);
+        } else {
+            font_files.push(path.clone());
         }
     }
+    font_files
+        .iter()
+        .map(|f| {
+            fs::read(f).map_err(|e| format!("Couldn't read font file {} ({})", f.display(), e))
+        })
+        .collect()
 }
 
-fn get_fontdata(font_filename: &str, subset_chars: &Vec<char>) -> fonts::FontData {
-    let f = FONTS_DIR
-        .get_file(&font_filename)
-        .expect("Couldn't open font file");
-    let data = f.contents().to_vec();
+/// Look up the glyph id for each of the given chars, or None if the font
+/// doesn't have that char.
+fn get_glyph_ids(data: &[u8], chars: &[char]) -> Result<Vec<Option<u16>>, String> {
+    let font_file = ReadScope::new(data)
+        .read::<allsorts::font_data::FontData<'_>>()
+        .map_err(|e| format!("Couldn't read font ({:?})", e))?;
+    let provider = font_file
+        .table_provider(0)
+        .map_err(|e| format!("Couldn't read font ({:?})", e))?;
+    let cmap_data = provider
+        .read_table_data(tag::CMAP)
+        .map_err(|e| format!("Couldn't read font's cmap table ({:?})", e))?;
+    let cmap = ReadScope::new(&cmap_data)
+        .read::<Cmap<'_>>()
+        .map_err(|e| format!("Couldn't read font's cmap table ({:?})", e))?;
+    let (_, cmap_subtable) = read_cmap_subtable(&cmap)
+        .map_err(|e| format!("Couldn't read font's cmap table ({:?})", e))?
+        .ok_or("Font has no usable cmap table")?;
+    Ok(chars
+        .iter()
+        .map(|c| match cmap_subtable.map_glyph(*c as u32) {
+            Ok(Some(0)) | Ok(None) | Err(_) => None,
+            Ok(Some(glyph_id)) => Some(glyph_id),
+        })
+        .collect())
+}
+
+fn get_fontdata(data: Vec<u8>, subset_chars: &[char]) -> Result<fonts::FontData, String> {
     if SKIP_FONT_SUBSETTING {
-        fonts::FontData::new(data, None).unwrap()
-    } else {
-        // To avoid bloating the PDF, we only embed the glyphs we need
-        let font_file = ReadScope::new(&data)
-            .read::<allsorts::font_data::FontData<'_>>()
-            .unwrap();
-        let provider = font_file.table_provider(0).unwrap();
-
-        let cmap_data = provider.read_table_data(tag::CMAP).unwrap();
-        let cmap = ReadScope::new(&cmap_data).read::<Cmap<'_>>().unwrap();
-        let (_, cmap_subtable) = read_cmap_subtable(&cmap).unwrap().unwrap();
-        let mut glyph_ids = vec![
-            0 as u16,
-            // Force unicode encoding:
-            cmap_subtable.map_glyph('€' as u32).unwrap().unwrap(),
-        ];
-        glyph_ids.extend(
-            subset_chars
-                .iter()
-                // TODO: pretty sure this'll fail disgracefully with unsupported chars
-                .map(|c| cmap_subtable.map_glyph(*c as u32).unwrap().unwrap()),
-        );
-        glyph_ids.sort();
-        glyph_ids.dedup();
+        return fonts::FontData::new(data, None).map_err(|e| format!("Couldn't load font ({})", e));
+    }
+    // To avoid bloating the PDF, we only embed the glyphs we need
+    let mut glyph_ids = vec![0_u16];
+    // Force unicode encoding, if the font has a euro sign:
+    glyph_ids.extend(get_glyph_ids(&data, &['€'])?.into_iter().flatten());
+    glyph_ids.extend(get_glyph_ids(&data, subset_chars)?.into_iter().flatten());
+    glyph_ids.sort();
+    glyph_ids.dedup();
+    fonts::FontData::new(subset_font(&data, &glyph_ids)?, None)
+        .map_err(|e| format!("Couldn't load font ({})", e))
+}
 
-        let new_font = subset(&provider, &glyph_ids).unwrap();
-        fonts::FontData::new(new_font, None).unwrap()
+/// A font with only the given glyphs, which have to start with 0 (.notdef).
+/// In the subset, each glyph's id is its index in the list.
+fn subset_font(data: &[u8], glyph_ids: &[u16]) -> Result<Vec<u8>, String> {
+    let font_file = ReadScope::new(data)
+        .read::<allsorts::font_data::FontData<'_>>()
+        .map_err(|e| format!("Couldn't read font ({:?})", e))?;
+    let provider = font_file
+        .table_provider(0)
+        .map_err(|e| format!("Couldn't read font ({:?})", e))?;
+    subset(&provider, glyph_ids).map_err(|e| format!("Couldn't subset font ({:?})", e))
+}
+
+/// PDF text strings are either ASCII, or UTF-16 with a byte order mark
+fn text_string(text: &str) -> lopdf::Object {
+    match text.is_ascii() {
+        true => lopdf::Object::String(text.as_bytes().to_vec(), lopdf::StringFormat::Literal),
+        false => {
+            let mut bytes = vec![0xfe, 0xff];
+            bytes.extend(text.encode_utf16().flat_map(|c| c.to_be_bytes()));
+            lopdf::Object::String(bytes, lopdf::StringFormat::Hexadecimal)
+        }
     }
 }
 
-pub fn create_pdf(
-    fountain_doc: FarceDocument,
+fn pdf_number(object: &lopdf::Object) -> Option<f32> {
+    match object {
+        lopdf::Object::Integer(i) => Some(*i as f32),
+        lopdf::Object::Real(r) => Some(*r as f32),
+        _ => None,
+    }
+}
+
+/// A page's MediaBox (left, bottom, right, top, in points), which it might
+/// inherit from its parent
+fn media_box(doc: &lopdf::Document, page_id: lopdf::ObjectId) -> Option<[f32; 4]> {
+    let mut node = doc.get_dictionary(page_id).ok()?;
+    loop {
+        if let Ok(media_box) = node.get(b"MediaBox").and_then(lopdf::Object::as_array) {
+            let numbers: Vec<f32> = media_box.iter().map(pdf_number).collect::<Option<_>>()?;
+            return match numbers[..] {
+                [left, bottom, right, top] => Some([left, bottom, right, top]),
+                _ => None,
+            };
+        }
+        let parent = node
+            .get(b"Parent")
+            .and_then(lopdf::Object::as_reference)
+            .ok()?;
+        node = doc.get_dictionary(parent).ok()?;
+    }
+}
+
+/// A PDF that's ready to render. genpdf does most of the work, and then we
+/// add the things it can't do itself, like the outline and the metadata.
+pub struct Pdf {
+    document: genpdf::Document,
+    outline: Vec<OutlineEntry>,
+    num_script_pages: usize,
+    metadata: Metadata,
+    reproducible: bool,
+    watermark: Option<String>,
+    watermark_fonts: Vec<Vec<u8>>,
     paper_size: PaperSize,
-) -> Result<genpdf::Document, String> {
+    imposition: Option<Imposition>,
+    crop_marks: bool,
+    layout: Layout,
+    page_lock: PageLock,
+}
+
+fn create_file(path: &Path) -> Result<io::BufWriter<fs::File>, String> {
+    fs::File::create(path)
+        .map(io::BufWriter::new)
+        .map_err(|e| format!("Couldn't create {} ({})", path.display(), e))
+}
+
+impl Pdf {
+    /// Where the script's page breaks are, so that it can be locked. If it
+    /// was already locked, that includes any A pages, and for sides it's
+    /// still the whole script.
+    pub fn page_lock(&self) -> PageLock {
+        self.page_lock.clone()
+    }
+
+    /// The script's pages (not counting the title page), and which page(s)
+    /// each of the document's elements is on and where. For a revised script
+    /// the elements include any OMITTED scenes, and for sides it's the whole
+    /// script, whose page numbers the sides keep.
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Render with genpdf, then add everything but the watermark, and scale
+    /// the pages down if they're too big for the paper
+    fn render_document(self) -> Result<lopdf::Document, String> {
+        let mut bytes = Vec::new();
+        self.document
+            .render(&mut bytes)
+            .map_err(|e| format!("Couldn't render PDF ({})", e))?;
+        let mut doc = lopdf::Document::load_mem(&bytes)
+            .map_err(|e| format!("Couldn't read back the rendered PDF ({})", e))?;
+        outline::add_outline(&mut doc, &self.outline, self.num_script_pages)?;
+        metadata::add_metadata(&mut doc, &self.metadata)?;
+        if !self.paper_size.fits_text() {
+            let (width, height) = self.paper_size.size();
+            imposition::fit_to_paper(
+                &mut doc,
+                width * 72.0 / inches(1.0),
+                height * 72.0 / inches(1.0),
+            )?;
+        }
+        Ok(doc)
+    }
+
+    /// Add the watermark, if any, then impose the pages and write the PDF
+    fn save(
+        mut doc: lopdf::Document,
+        watermark: Option<&str>,
+        watermark_fonts: &[Vec<u8>],
+        reproducible: bool,
+        imposition: Option<Imposition>,
+        crop_marks: bool,
+        mut w: impl Write,
+    ) -> Result<(), String> {
+        if let Some(watermark) = watermark {
+            watermark::add_watermark(&mut doc, watermark, watermark_fonts)?;
+        }
+        if let Some(imposition) = imposition {
+            imposition::impose(&mut doc, imposition, crop_marks)?;
+        }
+        if reproducible {
+            metadata::set_stable_id(&mut doc)?;
+        }
+        doc.save_to(&mut w)
+            .map_err(|e| format!("Couldn't write PDF ({})", e))
+    }
+
+    pub fn render(mut self, w: impl Write) -> Result<(), String> {
+        let watermark = self.watermark.take();
+        let watermark_fonts = std::mem::take(&mut self.watermark_fonts);
+        let (reproducible, imposition, crop_marks) =
+            (self.reproducible, self.imposition, self.crop_marks);
+        let doc = self.render_document()?;
+        Pdf::save(
+            doc,
+            watermark.as_deref(),
+            &watermark_fonts,
+            reproducible,
+            imposition,
+            crop_marks,
+            w,
+        )
+    }
+
+    pub fn render_to_file(self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.render(create_file(path.as_ref())?)
+    }
+
+    /// Write a copy of the PDF for each of the given watermarks (eg. the
+    /// names of the people it's going to) to the file that goes with it. The
+    /// script only gets laid out and rendered once.
+    pub fn render_batch(mut self, copies: &[(String, PathBuf)]) -> Result<(), String> {
+        let watermark_fonts = std::mem::take(&mut self.watermark_fonts);
+        let (reproducible, imposition, crop_marks) =
+            (self.reproducible, self.imposition, self.crop_marks);
+        let doc = self.render_document()?;
+        for (watermark, path) in copies {
+            let w = create_file(path)?;
+            Pdf::save(
+                doc.clone(),
+                Some(watermark),
+                &watermark_fonts,
+                reproducible,
+                imposition,
+                crop_marks,
+                w,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Bookmarks for the script pages, positioned at the lines they point to.
+/// The top margin is in inches.
+fn outline_entries(pages: &[Page], profile: &LayoutProfile, top_margin: f32) -> Vec<OutlineEntry> {
+    let mut entries = Vec::new();
+    for (i, page) in pages.iter().enumerate() {
+        for bookmark in &page.bookmarks {
+            let lines = (HEADER_LINES + bookmark.line) as f32;
+            entries.push(OutlineEntry {
+                depth: bookmark.depth,
+                title: bookmark.title.clone(),
+                page: i,
+                top: top_margin * 72.0 + lines * profile.font_size as f32,
+            });
+        }
+    }
+    entries
+}
+
+pub fn create_pdf(fountain_doc: FarceDocument, options: &PdfOptions) -> Result<Pdf, String> {
+    create_pdf_with_changes(fountain_doc, &[], options)
+}
+
+/// A PDF of a revised script, with an asterisk in the margin next to every
+/// line that's changed since the earlier draft
+pub fn create_revised_pdf(
+    fountain_doc: FarceDocument,
+    earlier: &FarceDocument,
+    options: &PdfOptions,
+) -> Result<Pdf, String> {
+    let revision = revision::compare(earlier, fountain_doc);
+    create_pdf_with_changes(revision.document, &revision.changes, options)
+}
+
+fn create_pdf_with_changes(
+    fountain_doc: FarceDocument,
+    changes: &[Change],
+    options: &PdfOptions,
+) -> Result<Pdf, String> {
     let title = {
         match fountain_doc.get_title() {
             Some(title) => title,
             None => constants::DEFAULT_TITLE,
         }
     };
-    let has_title_page = fountain_doc.has_title_page();
+    let watermark = match &options.watermark {
+        Some(watermark) => Some(watermark.clone()),
+        None => fountain_doc
+            .get_titlepage_field("Watermark")
+            .map(|value| metadata::plain_text(value, " ")),
+    }
+    .filter(|watermark| !watermark.trim().is_empty());
+    // A reproducible PDF can't use the current time, so it falls back on
+    // SOURCE_DATE_EPOCH, then the draft date, then the Unix epoch
+    let timestamp = match options.reproducible {
+        true => options.timestamp.or_else(Timestamp::from_source_date_epoch),
+        false => options.timestamp,
+    };
+    let mut metadata = Metadata::from_document(&fountain_doc, timestamp);
+    if options.reproducible && metadata.date.is_none() {
+        metadata.date = Some(Timestamp::from_unix(0));
+    }
+
+    let font_data = match &options.font {
+        Some(font_files) => font_files.read()?,
+        None => FontFamily {
+            regular: get_embedded_font("Courier Prime Regular.ttf"),
+            italic: get_embedded_font("Courier Prime Italic.ttf"),
+            bold: get_embedded_font("Courier Prime Bold.ttf"),
+            bold_italic: get_embedded_font("Courier Prime BoldItalic.ttf"),
+        },
+    };
+    let metrics = FontFamily {
+        regular: get_font_metrics(&font_data.regular)?,
+        italic: get_font_metrics(&font_data.italic)?,
+        bold: get_font_metrics(&font_data.bold)?,
+        bold_italic: get_font_metrics(&font_data.bold_italic)?,
+    };
+
+    let script_layout = layout::lay_out(
+        &fountain_doc,
+        options.format,
+        &options.profile,
+        PaginateOptions {
+            changes,
+            lock: options.page_lock.as_ref(),
+            highlight: options
+                .highlight
+                .as_deref()
+                .or_else(|| options.sides.as_ref()?.character.as_deref()),
+        },
+    );
+    let pages = script_layout.pages.clone();
+    let pages = match &options.sides {
+        Some(sides) => match sides.select(pages) {
+            pages if pages.is_empty() => {
+                return Err(String::from(
+                    "None of the scenes for the sides are in the script",
+                ))
+            }
+            pages => pages,
+        },
+        None => pages,
+    };
+
+    // Work out which font each char is going to come from. Anything the main
+    // font doesn't have comes from the first fallback font that does have it.
+    let mut all_chars = fountain_doc.get_all_chars();
+    all_chars.extend(options.header.chars().chain(options.footer.chars()));
+    all_chars.extend(
+        pages
+            .iter()
+            .flat_map(|page| page.number.iter().flat_map(|n| n.chars())),
+    );
+    if !changes.is_empty() {
+        all_chars.push('*');
+    }
+    all_chars.sort();
+    all_chars.dedup();
+    let fallback_data = read_font_files(&options.fallback_fonts)?;
+    // The watermark's drawn afterwards, and might be different for each copy
+    let mut watermark_fonts = vec![font_data.bold.clone()];
+    watermark_fonts.extend(fallback_data.iter().cloned());
+    let default_glyph_ids = get_glyph_ids(&font_data.regular, &all_chars)?;
+    let fallback_glyph_ids = fallback_data
+        .iter()
+        .map(|data| get_glyph_ids(data, &all_chars))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut default_chars = Vec::new();
+    let mut fallback_chars = vec![Vec::new(); fallback_data.len()];
+    for (i, c) in all_chars.iter().enumerate() {
+        if default_glyph_ids[i].is_some() {
+            default_chars.push(*c);
+        } else {
+            match fallback_glyph_ids.iter().position(|ids| ids[i].is_some()) {
+                Some(font_index) => fallback_chars[font_index].push(*c),
+                None => eprintln!(
+                    "Warning: No font has a glyph for '{}' (U+{:04X})",
+                    c, *c as u32
+                ),
+            }
+        }
+    }
 
-    let all_chars = fountain_doc.get_all_chars();
     let default_font = FontFamily {
-        regular: get_fontdata("Courier Prime Regular.ttf", &all_chars),
-        italic: get_fontdata("Courier Prime Italic.ttf", &all_chars),
-        bold: get_fontdata("Courier Prime Bold.ttf", &all_chars),
-        bold_italic: get_fontdata("Courier Prime BoldItalic.ttf", &all_chars),
+        regular: get_fontdata(font_data.regular, &default_chars)?,
+        italic: get_fontdata(font_data.italic, &default_chars)?,
+        bold: get_fontdata(font_data.bold, &default_chars)?,
+        bold_italic: get_fontdata(font_data.bold_italic, &default_chars)?,
     };
 
     let mut doc = genpdf::Document::new(default_font);
+    let mut fallbacks = FontFallbacks::default();
+    for (data, chars) in fallback_data.into_iter().zip(fallback_chars) {
+        if chars.is_empty() {
+            continue;
+        }
+        // Fallback fonts are single files, so they're used for all styles
+        let font_data = get_fontdata(data, &chars)?;
+        let family = doc.add_font_family(FontFamily {
+            regular: font_data.clone(),
+            italic: font_data.clone(),
+            bold: font_data.clone(),
+            bold_italic: font_data,
+        });
+        for c in chars {
+            fallbacks.char_families.insert(c, fallbacks.families.len());
+        }
+        fallbacks.families.push(family);
+    }
+
+    let paper_size = match options.paper_size {
+        Some(paper_size) => paper_size,
+        None => match fountain_doc.get_titlepage_field("Paper") {
+            Some(name) => {
+                PaperSize::from_name(&metadata::plain_text(name, " ")).unwrap_or_else(|e| {
+                    eprintln!("Warning: {} on the title page, using A4", e);
+                    PaperSize::A4
+                })
+            }
+            None => options.profile.paper.unwrap_or(PaperSize::A4),
+        },
+    };
+    paper_size.check_text_fits(&options.profile)?;
     doc.set_paper_size(paper_size.get_genpdf_paper_size());
     doc.set_title(title);
     doc.set_minimal_conformance();
     doc.set_line_spacing(1.0);
-    doc.set_font_size(12);
+    doc.set_font_size(options.profile.font_size);
+    let margins = &options.profile.margins;
+    let (extra_width, extra_height) = paper_size.extra_margins();
     let mut decorator = genpdf::SimplePageDecorator::new();
-    decorator.set_margins((inches(0.5), inches(0.8), inches(1.0), inches(1.5)));
-    decorator.set_header(move |page| {
-        let mut layout = elements::LinearLayout::vertical();
-        if has_title_page {
-            if page > 2 {
-                layout.push(
-                    elements::Paragraph::new(format!("{}.", page - 1)).aligned(Alignment::Right),
-                );
-                layout.push(elements::Break::new(3)); // Guestimate of 1" top margin
-            } else {
-                layout.push(elements::Break::new(4));
-            }
-        } else {
-            if page > 1 {
-                layout
-                    .push(elements::Paragraph::new(format!("{}.", page)).aligned(Alignment::Right));
-                layout.push(elements::Break::new(3)); // Guestimate of 1" top margin
-            } else {
-                layout.push(elements::Break::new(4));
-            }
-        }
-        layout.styled(style::Style::new().with_font_size(10))
-    });
+    decorator.set_margins((
+        inches(margins.top) + extra_height,
+        inches(margins.right) + extra_width,
+        inches(margins.bottom) + extra_height,
+        inches(margins.left) + extra_width,
+    ));
     doc.set_page_decorator(decorator);
 
-    if has_title_page {
-        doc.push(elements::Break::new(10));
-        doc.push(Paragraph::new(title).aligned(Alignment::Center));
-        doc.push(elements::Break::new(1));
-        match fountain_doc.get_titlepage_field("Author") {
-            Some(author_name) => {
-                doc.push(
-                    Paragraph::new({
-                        match fountain_doc.get_titlepage_field("Credit") {
-                            Some(credit) => credit,
-                            None => constants::DEFAULT_CREDIT,
-                        }
-                    })
-                    .aligned(Alignment::Center),
-                );
-                doc.push(elements::Break::new(1));
-                doc.push(Paragraph::new(author_name).aligned(Alignment::Center));
-            }
-            None => {}
-        }
-        doc.push(elements::PageBreak::new());
-    }
+    let front_pages = layout::front_pages(&fountain_doc, options.format, &options.profile);
     #[cfg(feature = "hyphenation")]
     {
         use hyphenation::Load;
@@ -253,40 +1066,35 @@ pub fn create_pdf(
         );
     }
 
-    for element in fountain_doc.elements {
-        match element {
-            FarceElement::FAction(action) => {
-                doc.push(render_inline_formatting(&action.text, action.is_centered));
-                doc.push(elements::Break::new(1));
-            }
-            FarceElement::FDialogue(dialogue) => {
-                doc.push(Paragraph::new(dialogue.character_line_as_text()).padded((
-                    0.0,
-                    0.0,
-                    0.0,
-                    inches(1.9),
-                )));
-                doc.push(render_inline_formatting(&dialogue.text, false).padded((
-                    0.0,
-                    inches(1.3),
-                    0.0,
-                    inches(0.875),
-                )));
-                doc.push(elements::Break::new(1));
-            }
-            FarceElement::FSceneHeading(scene_heading) => {
-                doc.push(elements::Paragraph::default().styled_string(
-                    format!("{}. {}", scene_heading.int_or_ext, scene_heading.text),
-                    style::Style::from(style::Effect::Bold),
-                ));
-                doc.push(elements::Break::new(1));
-            }
-            FarceElement::FPageBreak => {
-                doc.push(elements::PageBreak::new());
-            }
-        }
+    let top_margin = options.profile.margins.top + extra_height / inches(1.0);
+    let outline = outline_entries(&pages, &options.profile, top_margin);
+    let num_script_pages = pages.len();
+    if !front_pages.is_empty() || !pages.is_empty() {
+        let decorations = PageDecorations::new(&fountain_doc, options, &pages);
+        doc.push(ScreenplayPages {
+            front_pages,
+            pages,
+            next_page: 0,
+            fallbacks,
+            metrics,
+            profile: options.profile.clone(),
+            decorations,
+        });
     }
-    Ok(doc)
+    Ok(Pdf {
+        document: doc,
+        outline,
+        num_script_pages,
+        metadata,
+        reproducible: options.reproducible,
+        watermark,
+        watermark_fonts,
+        paper_size,
+        imposition: options.imposition,
+        crop_marks: options.crop_marks,
+        page_lock: PageLock::from_layout(&script_layout, &fountain_doc),
+        layout: script_layout,
+    })
 }
 
 #[cfg(test)]
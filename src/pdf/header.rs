@@ -0,0 +1,212 @@
+// Running headers and footers on the script pages. Each one has a template
+// for the left, center and right of the page, with placeholders like {page}
+// that get filled in for each page.
+
+use super::metadata::plain_text;
+use super::PdfOptions;
+use crate::document::FarceDocument;
+use crate::layout::Page;
+use genpdf::elements::Alignment;
+
+const PLACEHOLDERS: [&str; 5] = ["title", "draft_date", "revision", "scenes", "page"];
+
+/// The templates for a header or footer. Empty templates print nothing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeaderFooter {
+    pub left: String,
+    pub center: String,
+    pub right: String,
+}
+
+/// Replace each "{name}" in the template with its value, leaving anything
+/// that `value` doesn't know about alone
+fn fill(template: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some((start, len)) = rest
+        .find('{')
+        .and_then(|start| Some((start, rest[start..].find('}')?)))
+    {
+        text.push_str(&rest[..start]);
+        let placeholder = &rest[start..=start + len];
+        match value(&placeholder[1..len]) {
+            Some(value) => text.push_str(&value),
+            None => text.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+impl HeaderFooter {
+    /// The usual header, with just the page number on the right
+    pub fn page_number() -> HeaderFooter {
+        HeaderFooter {
+            right: String::from("{page}."),
+            ..HeaderFooter::default()
+        }
+    }
+
+    /// The header for revised pages, eg. "Blue Rev. 10/12/26" on the left
+    /// and the page number on the right
+    pub fn revision() -> HeaderFooter {
+        HeaderFooter {
+            left: String::from("{revision} Rev. {draft_date}"),
+            ..HeaderFooter::page_number()
+        }
+    }
+
+    /// Parse "left|center|right", eg. "{title}||{page}.". Any parts that are
+    /// left off are empty.
+    pub fn parse(text: &str) -> Result<HeaderFooter, String> {
+        let parts: Vec<&str> = text.split('|').collect();
+        if parts.len() > 3 {
+            return Err(format!(
+                "Too many parts in \"{}\" (it should be left|center|right)",
+                text
+            ));
+        }
+        for part in &parts {
+            let mut unknown = None;
+            fill(part, |name| {
+                if !PLACEHOLDERS.contains(&name) {
+                    unknown.get_or_insert_with(|| name.to_string());
+                }
+                Some(String::new())
+            });
+            if let Some(name) = unknown {
+                return Err(format!(
+                    "Unknown placeholder {{{}}} (it should be one of {})",
+                    name,
+                    PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+                ));
+            }
+        }
+        let part = |i: usize| parts.get(i).map_or(String::new(), |p| p.to_string());
+        Ok(HeaderFooter {
+            left: part(0),
+            center: part(1),
+            right: part(2),
+        })
+    }
+
+    /// All the characters in the templates, so they can go in the font subset
+    pub(super) fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        self.left
+            .chars()
+            .chain(self.center.chars())
+            .chain(self.right.chars())
+    }
+}
+
+/// The first and last scene on each page, counting a scene that carries on
+/// from the previous page
+fn scene_ranges(pages: &[Page]) -> Vec<Option<(usize, usize)>> {
+    let mut current = None;
+    pages
+        .iter()
+        .map(|page| {
+            let mut scenes = page
+                .bookmarks
+                .iter()
+                .filter_map(|b| b.scene.map(|scene| (b.line, scene)));
+            let first = match scenes.next() {
+                Some((0, scene)) => Some(scene),
+                Some((_, scene)) => current.or(Some(scene)),
+                None => current,
+            };
+            if let Some(last) = page.bookmarks.iter().rev().find_map(|b| b.scene) {
+                current = Some(last);
+            }
+            first.zip(current)
+        })
+        .collect()
+}
+
+/// The headers and footers for a document's script pages
+pub(super) struct PageDecorations {
+    header: HeaderFooter,
+    footer: HeaderFooter,
+    title: String,
+    draft_date: String,
+    revision: String,
+    scenes: Vec<Option<(usize, usize)>>,
+    numbers: Vec<String>,
+}
+
+impl PageDecorations {
+    pub(super) fn new(
+        fountain_doc: &FarceDocument,
+        options: &PdfOptions,
+        pages: &[Page],
+    ) -> PageDecorations {
+        let field = |name: &str| {
+            fountain_doc
+                .get_titlepage_field(name)
+                .map_or(String::new(), |value| plain_text(value, " "))
+        };
+        PageDecorations {
+            header: options.header.clone(),
+            footer: options.footer.clone(),
+            title: field("Title"),
+            draft_date: field("Draft date"),
+            revision: options
+                .revision_color
+                .clone()
+                .unwrap_or_else(|| field("Revision")),
+            scenes: scene_ranges(pages),
+            numbers: pages
+                .iter()
+                .enumerate()
+                .map(|(i, page)| page.display_number(i))
+                .collect(),
+        }
+    }
+
+    /// The text for each part of a header or footer, on the given page
+    /// (counting from 0). Like the page number always has, anything with the
+    /// page number in it gets left off page 1.
+    fn fill(&self, templates: &HeaderFooter, page: usize) -> Vec<(Alignment, String)> {
+        let parts = [
+            (Alignment::Left, &templates.left),
+            (Alignment::Center, &templates.center),
+            (Alignment::Right, &templates.right),
+        ];
+        parts
+            .into_iter()
+            .filter(|(_, template)| self.number(page) != "1" || !template.contains("{page}"))
+            .map(|(alignment, template)| {
+                let text = fill(template, |name| match name {
+                    "title" => Some(self.title.clone()),
+                    "draft_date" => Some(self.draft_date.clone()),
+                    "revision" => Some(self.revision.clone()),
+                    "scenes" => Some(match self.scenes.get(page).copied().flatten() {
+                        Some((first, last)) if first == last => first.to_string(),
+                        Some((first, last)) => format!("{}-{}", first, last),
+                        None => String::new(),
+                    }),
+                    "page" => Some(self.number(page)),
+                    _ => None,
+                });
+                (alignment, text.trim().to_string())
+            })
+            .filter(|(_, text)| !text.is_empty())
+            .collect()
+    }
+
+    fn number(&self, page: usize) -> String {
+        match self.numbers.get(page) {
+            Some(number) => number.clone(),
+            None => (page + 1).to_string(),
+        }
+    }
+
+    pub(super) fn header(&self, page: usize) -> Vec<(Alignment, String)> {
+        self.fill(&self.header, page)
+    }
+
+    pub(super) fn footer(&self, page: usize) -> Vec<(Alignment, String)> {
+        self.fill(&self.footer, page)
+    }
+}
@@ -0,0 +1,285 @@
+// Imposition: putting the rendered pages onto sheets of paper for printing.
+// 2-up puts two pages side by side on each landscape sheet, for table reads.
+// A booklet orders them so that the sheets can be printed double sided,
+// folded in half and stapled down the middle. Each page becomes a form
+// XObject, drawn scaled down onto its half of the sheet.
+//
+// Pages that are too big for the paper, because the text won't fit on it at
+// its normal size, get scaled down onto it too, one to a page.
+
+use super::media_box;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashMap;
+
+const CROP_MARGIN: f32 = 18.0; // Points around the pages, for the crop marks
+const CROP_MARK_GAP: f32 = 3.0; // Between a crop mark and the page
+const CROP_MARK_WIDTH: f32 = 0.25;
+
+/// How to put the pages on the sheets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Imposition {
+    TwoUp,   // In reading order, two to a sheet
+    Booklet, // Saddle stitched, with blank pages to make a multiple of 4
+}
+
+/// Which pages go on the left and right of each side of each sheet, counting
+/// from 0. None is a blank.
+pub(super) fn sheet_order(num_pages: usize, imposition: Imposition) -> Vec<[Option<usize>; 2]> {
+    let page = |i: usize| Some(i).filter(|&i| i < num_pages);
+    match imposition {
+        Imposition::TwoUp => (0..num_pages)
+            .step_by(2)
+            .map(|i| [page(i), page(i + 1)])
+            .collect(),
+        Imposition::Booklet => {
+            let n = num_pages.div_ceil(4) * 4;
+            (0..n / 4)
+                .flat_map(|i| {
+                    [
+                        [page(n - 1 - 2 * i), page(2 * i)],
+                        [page(2 * i + 1), page(n - 2 - 2 * i)],
+                    ]
+                })
+                .collect()
+        }
+    }
+}
+
+/// Where a page goes on a sheet: scaled, then moved
+#[derive(Clone, Copy, Debug)]
+struct Placement {
+    scale: f32,
+    x: f32,
+    y: f32,
+}
+
+impl Placement {
+    /// The content that moves whatever comes after it into place. It's
+    /// written out by hand, because lopdf only writes numbers to two decimal
+    /// places, and the scale needs more than that.
+    fn transform(&self) -> String {
+        format!(
+            "{} 0 0 {} {} {} cm\n",
+            self.scale, self.scale, self.x, self.y
+        )
+    }
+
+    /// The content that draws the named form XObject in place
+    fn draw(&self, name: &str) -> String {
+        format!("q\n{}/{} Do\nQ\n", self.transform(), name)
+    }
+}
+
+fn line(from: (f32, f32), to: (f32, f32)) -> Vec<Operation> {
+    vec![
+        Operation::new("m", vec![from.0.into(), from.1.into()]),
+        Operation::new("l", vec![to.0.into(), to.1.into()]),
+        Operation::new("S", vec![]),
+    ]
+}
+
+/// Crop marks at the corners of the pages, pointing out from the edges that
+/// get trimmed, and fold marks at the top and bottom of the spine
+fn crop_mark_operations(left: f32, bottom: f32, right: f32, top: f32) -> Vec<Operation> {
+    let length = CROP_MARGIN - 2.0 * CROP_MARK_GAP;
+    let (near, far) = (CROP_MARK_GAP, CROP_MARK_GAP + length);
+    let middle = (left + right) / 2.0;
+    let mut operations = vec![
+        Operation::new("q", vec![]),
+        Operation::new("w", vec![CROP_MARK_WIDTH.into()]),
+        Operation::new("G", vec![0.into()]),
+    ];
+    for y in [bottom, top] {
+        let outwards = if y == bottom { -1.0 } else { 1.0 };
+        operations.extend(line((left - near, y), (left - far, y)));
+        operations.extend(line((right + near, y), (right + far, y)));
+        for x in [left, middle, right] {
+            operations.extend(line((x, y + outwards * near), (x, y + outwards * far)));
+        }
+    }
+    operations.push(Operation::new("Q", vec![]));
+    operations
+}
+
+/// The page's resources, which it might inherit from its parent
+fn page_resources(doc: &Document, page_id: ObjectId) -> Object {
+    match doc.get_page_resources(page_id) {
+        (Some(resources), _) => Object::Dictionary(resources.clone()),
+        (None, ids) => match ids.first() {
+            Some(&id) => id.into(),
+            None => Object::Dictionary(Dictionary::new()),
+        },
+    }
+}
+
+/// Turn a page into a form XObject, that can be drawn onto a sheet
+fn page_form(doc: &mut Document, page_id: ObjectId, page: [f32; 4]) -> Result<ObjectId, String> {
+    let content = doc
+        .get_page_content(page_id)
+        .map_err(|e| format!("Couldn't read a page of the PDF ({})", e))?;
+    let resources = page_resources(doc, page_id);
+    let bounding_box: Vec<Object> = page.iter().map(|&n| n.into()).collect();
+    Ok(doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => bounding_box,
+            "Resources" => resources,
+        },
+        content,
+    )))
+}
+
+/// Point any outline entries at the sheets their pages have gone on
+fn move_destinations(doc: &mut Document, moved: &HashMap<ObjectId, (ObjectId, Placement)>) {
+    for object in doc.objects.values_mut() {
+        let dest = match object.as_dict_mut().and_then(|d| d.get_mut(b"Dest")) {
+            Ok(Object::Array(dest)) => dest,
+            _ => continue,
+        };
+        let (sheet_id, placement) = match dest.first().and_then(|o| o.as_reference().ok()) {
+            Some(page_id) => match moved.get(&page_id) {
+                Some(&moved) => moved,
+                None => continue,
+            },
+            None => continue,
+        };
+        dest[0] = sheet_id.into();
+        if let Some(top) = dest.get_mut(3) {
+            if let Some(y) = super::pdf_number(top) {
+                *top = (placement.y + y * placement.scale).into();
+            }
+        }
+    }
+}
+
+/// Scale every page down to fit the paper (width and height in points),
+/// centered on it
+pub(super) fn fit_to_paper(doc: &mut Document, width: f32, height: f32) -> Result<(), String> {
+    let mut moved = HashMap::new();
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in pages {
+        let [left, bottom, right, top] = match media_box(doc, page_id) {
+            Some(page) => page,
+            None => continue,
+        };
+        let scale = (width / (right - left)).min(height / (top - bottom));
+        let placement = Placement {
+            scale,
+            x: (width - (right - left) * scale) / 2.0 - left * scale,
+            y: (height - (top - bottom) * scale) / 2.0 - bottom * scale,
+        };
+        let transform = format!("q\n{}", placement.transform());
+        let scale_id = doc.add_object(Stream::new(dictionary! {}, transform.into_bytes()));
+        let restore_id = doc.add_object(Stream::new(dictionary! {}, b"Q\n".to_vec()));
+        let mut contents = vec![Object::from(scale_id)];
+        contents.extend(doc.get_page_contents(page_id).into_iter().map(Object::from));
+        contents.push(restore_id.into());
+        let media_box: Vec<Object> = vec![0.into(), 0.into(), width.into(), height.into()];
+        let page = doc
+            .get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)
+            .map_err(|e| format!("Couldn't read a page of the PDF ({})", e))?;
+        page.set("Contents", contents);
+        page.set("MediaBox", media_box);
+        for other_box in [&b"CropBox"[..], b"TrimBox", b"BleedBox", b"ArtBox"] {
+            page.remove(other_box);
+        }
+        moved.insert(page_id, (page_id, placement));
+    }
+    move_destinations(doc, &moved);
+    Ok(())
+}
+
+/// Put the pages onto landscape sheets the size of the pages, two to a side
+pub(super) fn impose(
+    doc: &mut Document,
+    imposition: Imposition,
+    crop_marks: bool,
+) -> Result<(), String> {
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let page = match pages.first().and_then(|&id| media_box(doc, id)) {
+        Some(page) => page,
+        None => return Ok(()),
+    };
+    let [left, bottom, right, top] = page;
+    let (width, height) = (right - left, top - bottom);
+    // The sheet is the page turned on its side
+    let (sheet_width, sheet_height) = (height, width);
+    let margin = if crop_marks { CROP_MARGIN } else { 0.0 };
+    let scale = ((sheet_width / 2.0 - margin) / width).min((sheet_height - 2.0 * margin) / height);
+    let middle = sheet_width / 2.0;
+    let y = (sheet_height - height * scale) / 2.0;
+
+    let pages_id = doc
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Pages"))
+        .and_then(Object::as_reference)
+        .map_err(|e| format!("Couldn't find the PDF's pages ({})", e))?;
+    let forms = pages
+        .iter()
+        .map(|&page_id| page_form(doc, page_id, page))
+        .collect::<Result<Vec<ObjectId>, String>>()?;
+    let mut moved = HashMap::new();
+    let mut sheets: Vec<Object> = Vec::new();
+    for side in sheet_order(pages.len(), imposition) {
+        let sheet_id = doc.new_object_id();
+        let mut content = String::new();
+        let mut xobjects = Dictionary::new();
+        for (slot, page) in side.iter().enumerate() {
+            let i = match page {
+                Some(i) => *i,
+                None => continue,
+            };
+            let placement = Placement {
+                scale,
+                x: middle - (1 - slot) as f32 * width * scale - left * scale,
+                y: y - bottom * scale,
+            };
+            let name = format!("Page{}", i + 1);
+            content.push_str(&placement.draw(&name));
+            xobjects.set(name, forms[i]);
+            moved.insert(pages[i], (sheet_id, placement));
+        }
+        let mut content = content.into_bytes();
+        if crop_marks {
+            let operations = crop_mark_operations(
+                middle - width * scale,
+                y,
+                middle + width * scale,
+                y + height * scale,
+            );
+            let marks = Content { operations }
+                .encode()
+                .map_err(|e| format!("Couldn't draw the crop marks ({})", e))?;
+            content.extend(marks);
+        }
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let media_box: Vec<Object> =
+            vec![0.into(), 0.into(), sheet_width.into(), sheet_height.into()];
+        doc.objects.insert(
+            sheet_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => media_box,
+                "Contents" => content_id,
+                "Resources" => dictionary! { "XObject" => xobjects },
+            }),
+        );
+        sheets.push(sheet_id.into());
+    }
+    let count = sheets.len() as i64;
+    let pages_dict = doc
+        .get_object_mut(pages_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|e| format!("Couldn't find the PDF's pages ({})", e))?;
+    pages_dict.set("Kids", sheets);
+    pages_dict.set("Count", count);
+    move_destinations(doc, &moved);
+    // The original pages aren't needed any more, and the forms have copies
+    // of their content
+    doc.prune_objects();
+    Ok(())
+}
@@ -0,0 +1,400 @@
+// The PDF's document information (title, author etc.), which goes in the
+// Info dictionary and again as XMP metadata. genpdf only sets the title, so
+// we fill in the rest afterwards, with lopdf.
+
+use super::text_string;
+use crate::document::FarceDocument;
+use crate::inline_parser::parse_inline;
+use lopdf::{dictionary, Document, Object, Stream, StringFormat};
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CREATOR: &str = "scenewriter";
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    let is_leap_year = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if is_leap_year => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A date and time, in UTC
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl Timestamp {
+    pub fn from_unix(seconds: i64) -> Timestamp {
+        // Days to year / month / day, per Howard Hinnant's civil_from_days
+        let days = seconds.div_euclid(86400);
+        let time = seconds.rem_euclid(86400) as u32;
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let day_of_era = z.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Timestamp {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+
+    pub fn now() -> Timestamp {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs() as i64);
+        Timestamp::from_unix(seconds)
+    }
+
+    /// The time from the SOURCE_DATE_EPOCH environment variable, which
+    /// reproducible builds use to pin timestamps, if it's set
+    pub fn from_source_date_epoch() -> Option<Timestamp> {
+        let value = env::var("SOURCE_DATE_EPOCH").ok()?;
+        match value.trim().parse() {
+            Ok(seconds) => Some(Timestamp::from_unix(seconds)),
+            Err(_) => {
+                eprintln!(
+                    "Warning: Ignoring SOURCE_DATE_EPOCH \"{}\", which isn't a number of seconds",
+                    value
+                );
+                None
+            }
+        }
+    }
+
+    fn from_date(year: i64, month: u32, day: u32) -> Option<Timestamp> {
+        match (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day) {
+            true => Some(Timestamp {
+                year,
+                month,
+                day,
+                hour: 0,
+                minute: 0,
+                second: 0,
+            }),
+            false => None,
+        }
+    }
+
+    /// Understands ISO style dates like "2023-04-01" or "2023-04-01T12:30:00",
+    /// dates written out like "1 April 2023" or "April 1st, 2023", which is
+    /// how draft dates tend to look, and numeric dates like "20/1/2012" where
+    /// it's clear which number is the day.
+    pub fn parse(text: &str) -> Option<Timestamp> {
+        let text = text.trim();
+        let (date, time) = text.split_once('T').unwrap_or((text, ""));
+        let numbers: Vec<&str> = date.split(['-', '/', '.']).collect();
+        if let [first, second, third] = numbers[..] {
+            let (a, b, c): (u32, u32, u32) = (
+                first.parse().ok()?,
+                second.parse().ok()?,
+                third.parse().ok()?,
+            );
+            // Year first, or else day and month in whichever order makes
+            // sense, if only one of them does
+            let (year, month, day) = match (first.len(), third.len()) {
+                (4, _) => (a, b, c),
+                (_, 4) if a > 12 && b <= 12 => (c, b, a),
+                (_, 4) if b > 12 && a <= 12 => (c, a, b),
+                _ => return None,
+            };
+            let mut timestamp = Timestamp::from_date(year.into(), month, day)?;
+            if !time.is_empty() {
+                let time: Vec<u32> = time
+                    .trim_end_matches('Z')
+                    .split(':')
+                    .map(|n| n.parse().ok())
+                    .collect::<Option<_>>()?;
+                if let [hour, minute, second] = time[..] {
+                    timestamp.hour = hour.min(23);
+                    timestamp.minute = minute.min(59);
+                    timestamp.second = second.min(59);
+                }
+            }
+            return Some(timestamp);
+        }
+        let words: Vec<String> = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect();
+        // At least the first three letters of a month's name
+        let month = |word: &str| match word.len() >= 3 {
+            true => MONTHS.iter().position(|m| m.starts_with(word)),
+            false => None,
+        };
+        // Allowing for "1st", "2nd" etc.
+        let day = |word: &str| word.trim_end_matches(char::is_alphabetic).parse().ok();
+        let (month, day, year) = match &words[..] {
+            [d, m, y] if month(m).is_some() => (month(m)?, day(d)?, y),
+            [m, d, y] => (month(m)?, day(d)?, y),
+            _ => return None,
+        };
+        Timestamp::from_date(year.parse().ok()?, month as u32 + 1, day)
+    }
+
+    /// Like "D:20230401123000Z"
+    fn pdf_date(&self) -> String {
+        format!(
+            "D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+
+    /// Like "2023-04-01T12:30:00Z"
+    fn xmp_date(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    pub date: Option<Timestamp>,
+}
+
+/// A title page field as plain text, without any formatting, and all on one
+/// line
+pub(super) fn plain_text(text: &str, separator: &str) -> String {
+    text.lines()
+        .map(|line| match parse_inline(line.trim()) {
+            Ok((_, expressions)) => expressions.iter().map(|e| e.as_text()).collect(),
+            Err(_) => line.trim().to_string(),
+        })
+        .filter(|line: &String| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+impl Metadata {
+    /// The metadata from the document's title page. The dates come from the
+    /// timestamp if there is one, or else the draft date.
+    pub(super) fn from_document(
+        fountain_doc: &FarceDocument,
+        timestamp: Option<Timestamp>,
+    ) -> Metadata {
+        let field = |name: &str, separator: &str| {
+            fountain_doc
+                .get_titlepage_field(name)
+                .map(|value| plain_text(value, separator))
+                .filter(|value| !value.is_empty())
+        };
+        let draft_date = fountain_doc.get_titlepage_field("Draft date");
+        let date = timestamp.or_else(|| {
+            let draft_date = draft_date?;
+            let date = Timestamp::parse(draft_date);
+            if date.is_none() {
+                eprintln!(
+                    "Warning: Couldn't understand the draft date \"{}\", so the PDF won't use it",
+                    draft_date
+                );
+            }
+            date
+        });
+        Metadata {
+            title: field("Title", " "),
+            author: field("Author", ", ").or_else(|| field("Authors", ", ")),
+            subject: field("Source", " "),
+            keywords: field("Keywords", ", "),
+            date,
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xmp_packet(metadata: &Metadata, producer: &str, date: &Timestamp) -> String {
+    let mut properties = vec![String::from("<dc:format>application/pdf</dc:format>")];
+    let alt = |name: &str, value: &str| {
+        format!(
+            "<{0}><rdf:Alt><rdf:li xml:lang=\"x-default\">{1}</rdf:li></rdf:Alt></{0}>",
+            name,
+            escape_xml(value)
+        )
+    };
+    if let Some(title) = &metadata.title {
+        properties.push(alt("dc:title", title));
+    }
+    if let Some(author) = &metadata.author {
+        properties.push(format!(
+            "<dc:creator><rdf:Seq><rdf:li>{}</rdf:li></rdf:Seq></dc:creator>",
+            escape_xml(author)
+        ));
+    }
+    if let Some(subject) = &metadata.subject {
+        properties.push(alt("dc:description", subject));
+    }
+    if let Some(keywords) = &metadata.keywords {
+        properties.push(format!(
+            "<pdf:Keywords>{}</pdf:Keywords>",
+            escape_xml(keywords)
+        ));
+    }
+    properties.push(format!("<pdf:Producer>{}</pdf:Producer>", producer));
+    properties.push(format!("<xmp:CreatorTool>{}</xmp:CreatorTool>", CREATOR));
+    properties.push(format!(
+        "<xmp:CreateDate>{}</xmp:CreateDate>",
+        date.xmp_date()
+    ));
+    properties.push(format!(
+        "<xmp:ModifyDate>{}</xmp:ModifyDate>",
+        date.xmp_date()
+    ));
+    properties.push(format!(
+        "<xmp:MetadataDate>{}</xmp:MetadataDate>",
+        date.xmp_date()
+    ));
+    format!(
+        concat!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n",
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+            "<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+            "<rdf:Description rdf:about=\"\"",
+            " xmlns:dc=\"http://purl.org/dc/elements/1.1/\"",
+            " xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\"",
+            " xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\">\n",
+            "{}\n",
+            "</rdf:Description>\n",
+            "</rdf:RDF>\n",
+            "</x:xmpmeta>\n",
+            "<?xpacket end=\"w\"?>"
+        ),
+        properties.join("\n")
+    )
+}
+
+/// Fill in the PDF's Info dictionary, and replace any XMP metadata it had
+pub(super) fn add_metadata(doc: &mut Document, metadata: &Metadata) -> Result<(), String> {
+    let date = metadata.date.unwrap_or_else(Timestamp::now);
+    let producer = format!("{} {}", CREATOR, env!("CARGO_PKG_VERSION"));
+
+    let mut info = dictionary! {
+        "Creator" => text_string(CREATOR),
+        "Producer" => text_string(&producer),
+        "CreationDate" => text_string(&date.pdf_date()),
+        "ModDate" => text_string(&date.pdf_date()),
+    };
+    let fields = [
+        ("Title", &metadata.title),
+        ("Author", &metadata.author),
+        ("Subject", &metadata.subject),
+        ("Keywords", &metadata.keywords),
+    ];
+    for (key, value) in fields {
+        if let Some(value) = value {
+            info.set(key, text_string(value));
+        }
+    }
+    let info_id = match doc.trailer.get(b"Info").and_then(Object::as_reference) {
+        Ok(id) => {
+            let existing = doc
+                .get_object_mut(id)
+                .and_then(Object::as_dict_mut)
+                .map_err(|e| format!("Couldn't read the PDF's Info dictionary ({})", e))?;
+            existing.extend(&info);
+            id
+        }
+        Err(_) => doc.add_object(info),
+    };
+    doc.trailer.set("Info", info_id);
+
+    let xmp = xmp_packet(metadata, &producer, &date);
+    let stream = Stream::new(
+        dictionary! {
+            "Type" => "Metadata",
+            "Subtype" => "XML",
+        },
+        xmp.into_bytes(),
+    )
+    .with_compression(false);
+    let metadata_id = doc.add_object(stream);
+    let catalog_id = doc
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|e| format!("Couldn't find the PDF's catalog ({})", e))?;
+    let catalog = doc
+        .get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|e| format!("Couldn't find the PDF's catalog ({})", e))?;
+    let old_metadata = catalog.get(b"Metadata").and_then(Object::as_reference).ok();
+    catalog.set("Metadata", metadata_id);
+    // Otherwise the old packet would still get written out, unused
+    if let Some(id) = old_metadata {
+        doc.objects.remove(&id);
+    }
+    Ok(())
+}
+
+/// A 128 bit FNV-1a hash, which is plenty for telling PDFs apart
+fn fingerprint(bytes: &[u8]) -> [u8; 16] {
+    const OFFSET_BASIS: u128 = 0x6c62272e07bb014262b821756295c58d;
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    let mut hash = OFFSET_BASIS;
+    for byte in bytes {
+        hash ^= u128::from(*byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash.to_be_bytes()
+}
+
+/// Replace the random document ID that printpdf makes up with one that's a
+/// hash of the PDF itself, so the same script always gives the same file
+pub(super) fn set_stable_id(doc: &mut Document) -> Result<(), String> {
+    doc.trailer.remove(b"ID");
+    if let Ok(info_id) = doc.trailer.get(b"Info").and_then(Object::as_reference) {
+        if let Ok(info) = doc.get_object_mut(info_id).and_then(Object::as_dict_mut) {
+            info.remove(b"Identifier");
+        }
+    }
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)
+        .map_err(|e| format!("Couldn't write PDF ({})", e))?;
+    let id = Object::String(fingerprint(&bytes).to_vec(), StringFormat::Hexadecimal);
+    doc.trailer.set("ID", vec![id.clone(), id]);
+    Ok(())
+}
@@ -0,0 +1,134 @@
+// genpdf can't make a document outline (the bookmarks a PDF viewer shows in
+// its sidebar), so we add one to the rendered PDF afterwards, with lopdf.
+
+use super::{media_box, text_string};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+
+/// A bookmark, pointing at a place on one of the script pages
+#[derive(Clone, Debug)]
+pub(super) struct OutlineEntry {
+    pub depth: usize, // Entries nest inside the nearest shallower entry before them
+    pub title: String,
+    pub page: usize, // Counting from the first script page, after the title page etc.
+    pub top: f32,    // Points down from the top of the page
+}
+
+/// The height of a page in points
+fn page_height(doc: &Document, page_id: ObjectId) -> Option<f32> {
+    let [_, bottom, _, top] = media_box(doc, page_id)?;
+    Some(top - bottom)
+}
+
+/// For each entry, the index of the entry it's nested inside
+fn parents(entries: &[OutlineEntry]) -> Vec<Option<usize>> {
+    let mut parents = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        while stack
+            .last()
+            .is_some_and(|&j| entries[j].depth >= entry.depth)
+        {
+            stack.pop();
+        }
+        parents.push(stack.last().copied());
+        stack.push(i);
+    }
+    parents
+}
+
+/// Link a list of sibling outline items together, and to their parent
+fn link_children(
+    parent: &mut Dictionary,
+    children: &[usize],
+    items: &mut [Dictionary],
+    ids: &[ObjectId],
+) {
+    if let (Some(&first), Some(&last)) = (children.first(), children.last()) {
+        parent.set("First", ids[first]);
+        parent.set("Last", ids[last]);
+    }
+    for pair in children.windows(2) {
+        items[pair[0]].set("Next", ids[pair[1]]);
+        items[pair[1]].set("Prev", ids[pair[0]]);
+    }
+}
+
+/// Add an outline to a rendered PDF whose last `num_script_pages` pages are
+/// the script itself. All the entries start off open.
+pub(super) fn add_outline(
+    doc: &mut Document,
+    entries: &[OutlineEntry],
+    num_script_pages: usize,
+) -> Result<(), String> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let first_script_page = pages.len().saturating_sub(num_script_pages);
+
+    let outlines_id = doc.new_object_id();
+    let ids: Vec<ObjectId> = entries.iter().map(|_| doc.new_object_id()).collect();
+    let parents = parents(entries);
+    let mut items = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let page_id = pages
+            .get(first_script_page + entry.page)
+            .copied()
+            .ok_or("Outline entry is past the end of the PDF")?;
+        let dest = match page_height(doc, page_id) {
+            Some(height) => vec![
+                page_id.into(),
+                "XYZ".into(),
+                Object::Null,
+                (height - entry.top).into(),
+                Object::Null,
+            ],
+            None => vec![page_id.into(), "Fit".into()],
+        };
+        // Everything's open, so the count is all the entries below this one
+        let descendants = entries[i + 1..]
+            .iter()
+            .take_while(|e| e.depth > entry.depth)
+            .count();
+        items.push(dictionary! {
+            "Title" => text_string(&entry.title),
+            "Parent" => parents[i].map_or(outlines_id, |p| ids[p]),
+            "Dest" => dest,
+            "Count" => descendants as i64,
+        });
+    }
+    let mut outlines = dictionary! {
+        "Type" => "Outlines",
+        "Count" => entries.len() as i64,
+    };
+    let top_level: Vec<usize> = (0..entries.len())
+        .filter(|&i| parents[i].is_none())
+        .collect();
+    link_children(&mut outlines, &top_level, &mut items, &ids);
+    for i in 0..entries.len() {
+        let children: Vec<usize> = (0..entries.len())
+            .filter(|&j| parents[j] == Some(i))
+            .collect();
+        let mut item = std::mem::take(&mut items[i]);
+        link_children(&mut item, &children, &mut items, &ids);
+        items[i] = item;
+    }
+
+    doc.objects
+        .insert(outlines_id, Object::Dictionary(outlines));
+    for (id, item) in ids.into_iter().zip(items) {
+        doc.objects.insert(id, Object::Dictionary(item));
+    }
+    let catalog_id = doc
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .map_err(|e| format!("Couldn't find the PDF's catalog ({})", e))?;
+    let catalog = doc
+        .get_object_mut(catalog_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(|e| format!("Couldn't find the PDF's catalog ({})", e))?;
+    catalog.set("Outlines", outlines_id);
+    catalog.set("PageMode", "UseOutlines");
+    Ok(())
+}
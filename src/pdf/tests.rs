@@ -2,8 +2,48 @@
 mod tests {
     use crate::{
         document::{Action, FarceDocument, FarceElement},
-        pdf::create_pdf,
+        pdf::{
+            create_pdf, get_embedded_font, get_font_metrics,
+            header::PageDecorations,
+            imposition::{fit_to_paper, impose, sheet_order, Imposition},
+            inches, media_box,
+            metadata::{add_metadata, set_stable_id, Metadata},
+            outline::{add_outline, OutlineEntry},
+            watermark::add_watermark,
+            FontFallbacks, FontFiles, HeaderFooter, PaperSize, PdfOptions, Timestamp,
+        },
     };
+    use lopdf::{dictionary, Object, ObjectId, StringFormat};
+
+    fn render_action(text: &str) -> lopdf::Document {
+        let fdoc = FarceDocument {
+            title_page: None,
+            elements: vec![FarceElement::FAction(Action {
+                text: text.to_string(),
+                is_centered: false,
+            })],
+        };
+        let mut bytes = Vec::new();
+        create_pdf(fdoc, &PdfOptions::default())
+            .unwrap()
+            .render(&mut bytes)
+            .unwrap();
+        lopdf::Document::load_mem(&bytes).unwrap()
+    }
+
+    fn count_lines_drawn(pdf: &lopdf::Document) -> usize {
+        let mut count = 0;
+        for page_id in pdf.page_iter() {
+            let content = pdf.get_page_content(page_id).unwrap();
+            let content = lopdf::content::Content::decode(&content).unwrap();
+            count += content
+                .operations
+                .iter()
+                .filter(|op| op.operator == "l")
+                .count();
+        }
+        count
+    }
 
     #[test]
     fn test_create_pdf() {
@@ -17,9 +57,708 @@ mod tests {
             title_page: None,
             elements: v,
         };
-        let _pdf_doc = create_pdf(fdoc, crate::pdf::PaperSize::A4).unwrap();
+        let _pdf_doc = create_pdf(fdoc, &PdfOptions::default()).unwrap();
         _pdf_doc.render_to_file("t.pdf").unwrap();
         // Should probably actually test the pdf somehow.
-        // NB As of now, underlined text is not supported
+    }
+
+    #[test]
+    fn test_reproducible_pdf() {
+        // Everything that gets added to genpdf's output has to come out the
+        // same every time too
+        let input = "Title: Brick & Steel
+Author: Stu Maschwitz
+Draft date: 1/20/2012
+
+# Act One
+
+INT. WAREHOUSE - NIGHT
+
+Brick and Steel wait.
+
+BRICK
+Now?
+
+STEEL
+Now.
+";
+        let options = PdfOptions {
+            reproducible: true,
+            watermark: Some("Copy 1".to_string()),
+            ..PdfOptions::default()
+        };
+        let render = || {
+            let (_, fdoc) = crate::parser::parse_fountain(input).unwrap();
+            let mut bytes = Vec::new();
+            create_pdf(fdoc, &options)
+                .unwrap()
+                .render(&mut bytes)
+                .unwrap();
+            bytes
+        };
+        let first = render();
+        assert_eq!(first, render());
+
+        let pdf = lopdf::Document::load_mem(&first).unwrap();
+        assert!(pdf.catalog().unwrap().get(b"Outlines").is_ok());
+        let info = pdf.trailer.get(b"Info").and_then(Object::as_reference);
+        let info = pdf.get_dictionary(info.unwrap()).unwrap();
+        assert!(info.get(b"Author").is_ok());
+        assert!(info.get(b"CreationDate").is_ok());
+    }
+
+    #[test]
+    fn test_underline() {
+        let plain = render_action("Nothing to see here.");
+        assert_eq!(count_lines_drawn(&plain), 0);
+
+        // One underline per line of underlined text
+        let underlined = render_action(
+            "Some _underlined text that's long enough to wrap onto a second line_, and some that isn't.",
+        );
+        assert_eq!(count_lines_drawn(&underlined), 2);
+
+        // On the title page too
+        let (_, fdoc) =
+            crate::parser::parse_fountain("Title: _Brick & Steel_\n\nHello.\n").unwrap();
+        let mut bytes = Vec::new();
+        create_pdf(fdoc, &PdfOptions::default())
+            .unwrap()
+            .render(&mut bytes)
+            .unwrap();
+        let pdf = lopdf::Document::load_mem(&bytes).unwrap();
+        assert_eq!(count_lines_drawn(&pdf), 1);
+    }
+
+    #[test]
+    fn test_missing_glyphs() {
+        // Courier Prime doesn't have these, but we should get a PDF anyway
+        render_action("Γειά σου, 你好");
+    }
+
+    #[test]
+    fn test_fallback_font() {
+        // Courier Prime doesn't have Greek, but DejaVu Sans Mono does
+        let font_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fonts/truetype/DejaVu Sans Mono/DejaVuSansMono.ttf"
+        );
+        let (_, fdoc) = crate::parser::parse_fountain("Hello, Γειά σου.\n").unwrap();
+        let options = PdfOptions {
+            fallback_fonts: vec![font_path.into()],
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        create_pdf(fdoc, &options)
+            .unwrap()
+            .render(&mut bytes)
+            .unwrap();
+        let pdf = lopdf::Document::load_mem(&bytes).unwrap();
+
+        // The text is printed in both fonts
+        let page_id = pdf.page_iter().next().unwrap();
+        let content = pdf.get_page_content(page_id).unwrap();
+        let content = lopdf::content::Content::decode(&content).unwrap();
+        let mut fonts: Vec<&[u8]> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Tf")
+            .map(|op| op.operands[0].as_name().unwrap())
+            .collect();
+        fonts.sort();
+        fonts.dedup();
+        assert_eq!(fonts.len(), 2);
+    }
+
+    #[test]
+    fn test_split_runs() {
+        let mut fallbacks = FontFallbacks::default();
+        fallbacks.char_families.insert('你', 0);
+        fallbacks.char_families.insert('好', 0);
+        fallbacks.char_families.insert('Γ', 1);
+        assert_eq!(
+            fallbacks.split_runs("Hi 你好Γ!"),
+            vec![
+                ("Hi ", None),
+                ("你好", Some(0)),
+                ("Γ", Some(1)),
+                ("!", None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_font_metrics() {
+        let metrics = get_font_metrics(&get_embedded_font("Courier Prime Bold.ttf")).unwrap();
+        assert_eq!(metrics.units_per_em, 2048.0);
+        assert_eq!(metrics.ascent, 1421.0);
+        assert_eq!(metrics.underline_position, -130.0);
+        assert_eq!(metrics.underline_thickness, 180.0);
+    }
+
+    #[test]
+    fn test_user_font() {
+        let font_path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/fonts/truetype/Courier Prime/Courier Prime Italic.ttf"
+        );
+        let fdoc = FarceDocument {
+            title_page: None,
+            elements: vec![FarceElement::FAction(Action {
+                text: "Some _underlined_ text".to_string(),
+                is_centered: false,
+            })],
+        };
+        let options = PdfOptions {
+            font: Some(FontFiles {
+                regular: font_path.into(),
+                bold: None,
+                italic: None,
+                bold_italic: None,
+            }),
+            ..Default::default()
+        };
+        assert!(create_pdf(fdoc, &options).is_ok());
+
+        let options = PdfOptions {
+            font: Some(FontFiles {
+                regular: "no-such-font.ttf".into(),
+                bold: None,
+                italic: None,
+                bold_italic: None,
+            }),
+            ..Default::default()
+        };
+        let fdoc = FarceDocument {
+            title_page: None,
+            elements: Vec::new(),
+        };
+        assert!(create_pdf(fdoc, &options).is_err());
+    }
+
+    /// A blank PDF with some A4 pages
+    fn blank_pdf(num_pages: usize) -> lopdf::Document {
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..num_pages)
+            .map(|_| {
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                })
+                .into()
+            })
+            .collect();
+        let pages = dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => num_pages as i64,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+        };
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    #[test]
+    fn test_add_outline() {
+        let entry = |depth, title: &str, page| OutlineEntry {
+            depth,
+            title: title.to_string(),
+            page,
+            top: 72.0,
+        };
+        let entries = vec![
+            entry(1, "Act One", 0),
+            entry(2, "1 INT. HOUSE - DAY", 0),
+            entry(2, "2 EXT. GARDEN - NIGHT", 1),
+            entry(1, "Act Two \u{2013} Später", 2),
+        ];
+        // The first page is the title page
+        let mut doc = blank_pdf(4);
+        add_outline(&mut doc, &entries, 3).unwrap();
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+
+        let catalog = doc.catalog().unwrap();
+        assert_eq!(
+            catalog.get(b"PageMode").unwrap().as_name().unwrap(),
+            b"UseOutlines"
+        );
+        let outlines_id = catalog.get(b"Outlines").unwrap().as_reference().unwrap();
+        let outlines = doc.get_dictionary(outlines_id).unwrap();
+        assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 4);
+
+        let item = |dict: &lopdf::Dictionary, key: &[u8]| {
+            let id = dict.get(key).unwrap().as_reference().unwrap();
+            doc.get_dictionary(id).unwrap().clone()
+        };
+        let act_one = item(outlines, b"First");
+        assert_eq!(act_one.get(b"Title").unwrap().as_str().unwrap(), b"Act One");
+        assert_eq!(act_one.get(b"Count").unwrap().as_i64().unwrap(), 2);
+        let dest = act_one.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), pages[1]);
+        assert_eq!(dest[3].as_f64().unwrap(), 842.0 - 72.0);
+
+        let scene_two = item(&act_one, b"Last");
+        assert_eq!(
+            scene_two.get(b"Title").unwrap().as_str().unwrap(),
+            b"2 EXT. GARDEN - NIGHT"
+        );
+        let dest = scene_two.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), pages[2]);
+        let scene_one = item(&scene_two, b"Prev");
+        assert_eq!(
+            scene_one.get(b"Title").unwrap().as_str().unwrap(),
+            b"1 INT. HOUSE - DAY"
+        );
+
+        let act_two = item(&act_one, b"Next");
+        assert_eq!(act_two.get(b"Count").unwrap().as_i64().unwrap(), 0);
+        let title = act_two.get(b"Title").unwrap().as_str().unwrap();
+        assert_eq!(&title[..4], &[0xfe, 0xff, 0, b'A']);
+        let last = item(outlines, b"Last");
+        assert_eq!(last.get(b"Title").unwrap().as_str().unwrap(), title);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let date = |year, month, day| Timestamp {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        };
+        assert_eq!(Timestamp::from_unix(0), date(1970, 1, 1));
+        assert_eq!(
+            Timestamp::from_unix(1709210096),
+            Timestamp {
+                hour: 12,
+                minute: 34,
+                second: 56,
+                ..date(2024, 2, 29)
+            }
+        );
+        assert_eq!(Timestamp::parse("2023-04-01"), Some(date(2023, 4, 1)));
+        assert_eq!(Timestamp::parse("1 April 2023"), Some(date(2023, 4, 1)));
+        assert_eq!(Timestamp::parse("Dec 25th, 2022"), Some(date(2022, 12, 25)));
+        assert_eq!(
+            Timestamp::parse("2024-02-29T12:34:56Z"),
+            Timestamp::parse("29 FEBRUARY 2024").map(|t| Timestamp {
+                hour: 12,
+                minute: 34,
+                second: 56,
+                ..t
+            })
+        );
+        assert_eq!(Timestamp::parse("20/1/2012"), Some(date(2012, 1, 20)));
+        // Could be February or January
+        assert_eq!(Timestamp::parse("1/2/2012"), None);
+        assert_eq!(Timestamp::parse("Final draft"), None);
+        assert_eq!(Timestamp::parse("2023-13-01"), None);
+        // Days that aren't in the month
+        assert_eq!(Timestamp::parse("31/02/2024"), None);
+        assert_eq!(Timestamp::parse("2023-04-31"), None);
+        assert_eq!(Timestamp::parse("29 February 2023"), None);
+        assert_eq!(Timestamp::parse("2000-02-29"), Some(date(2000, 2, 29)));
+        assert_eq!(Timestamp::parse("1900-02-29"), None);
+    }
+
+    #[test]
+    fn test_add_metadata() {
+        let input = "Title: _**BRICK & STEEL**_
+Credit: Written by
+Author: Stu Maschwitz
+Source: Story by KTM
+Keywords: heist, noir
+Draft date: 1/20/2012
+";
+        let (_, fdoc) = crate::parser::parse_fountain(input).unwrap();
+        let metadata = Metadata::from_document(&fdoc, None);
+        assert_eq!(metadata.title.as_deref(), Some("BRICK & STEEL"));
+        assert_eq!(metadata.author.as_deref(), Some("Stu Maschwitz"));
+        assert_eq!(metadata.subject.as_deref(), Some("Story by KTM"));
+        assert_eq!(metadata.date, Timestamp::parse("2012-01-20"));
+
+        let timestamp = Timestamp::parse("2023-04-01").unwrap();
+        let metadata = Metadata::from_document(&fdoc, Some(timestamp));
+        let mut doc = blank_pdf(1);
+        add_metadata(&mut doc, &metadata).unwrap();
+
+        let info_id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        let info = doc.get_dictionary(info_id).unwrap();
+        let field = |key: &[u8]| info.get(key).unwrap().as_str().unwrap().to_vec();
+        assert_eq!(field(b"Title"), b"BRICK & STEEL");
+        assert_eq!(field(b"Keywords"), b"heist, noir");
+        assert_eq!(field(b"Creator"), b"scenewriter");
+        assert_eq!(field(b"CreationDate"), b"D:20230401000000Z");
+        assert_eq!(field(b"ModDate"), b"D:20230401000000Z");
+
+        let metadata_id = doc
+            .catalog()
+            .unwrap()
+            .get(b"Metadata")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let xmp = doc.get_object(metadata_id).unwrap().as_stream().unwrap();
+        let xmp = String::from_utf8(xmp.content.clone()).unwrap();
+        assert!(xmp.contains("<rdf:li xml:lang=\"x-default\">BRICK &amp; STEEL</rdf:li>"));
+        assert!(xmp.contains("<dc:creator><rdf:Seq><rdf:li>Stu Maschwitz</rdf:li>"));
+        assert!(xmp.contains("<xmp:CreateDate>2023-04-01T00:00:00Z</xmp:CreateDate>"));
+    }
+
+    #[test]
+    fn test_set_stable_id() {
+        // The same PDF, but with different random IDs, like printpdf makes
+        let render = |random_id: &str, num_pages| {
+            let mut doc = blank_pdf(num_pages);
+            let id = Object::String(random_id.as_bytes().to_vec(), StringFormat::Literal);
+            doc.trailer.set("ID", vec![id.clone(), id]);
+            let info_id = doc.add_object(dictionary! {
+                "Identifier" => Object::string_literal(random_id),
+            });
+            doc.trailer.set("Info", info_id);
+            set_stable_id(&mut doc).unwrap();
+            let mut bytes = Vec::new();
+            doc.save_to(&mut bytes).unwrap();
+            bytes
+        };
+        let first = render("JRUMKWIYLPXNQKDAHTOVZEBGFSCRYWJM", 1);
+        assert_eq!(first, render("QWERTYUIOPASDFGHJKLZXCVBNMQWERTY", 1));
+        assert_ne!(first, render("JRUMKWIYLPXNQKDAHTOVZEBGFSCRYWJM", 2));
+        let text = String::from_utf8_lossy(&first);
+        assert!(!text.contains("JRUMKWIYLPXNQKDAHTOVZEBGFSCRYWJM"));
+    }
+
+    #[test]
+    fn test_header_footer() {
+        let header = HeaderFooter::parse("{revision} Rev. {draft_date}||{page}.").unwrap();
+        assert_eq!(header.left, "{revision} Rev. {draft_date}");
+        assert_eq!(header.center, "");
+        assert_eq!(header.right, "{page}.");
+        assert_eq!(
+            HeaderFooter::parse("||{page}.").unwrap(),
+            HeaderFooter::page_number()
+        );
+        assert!(HeaderFooter::parse("{pages}").is_err());
+        assert!(HeaderFooter::parse("a|b|c|d").is_err());
+
+        let mut input =
+            String::from("Title: **Big Fish**\nRevision: Blue\nDraft date: 10/12/26\n\n");
+        for scene in [
+            "INT. A HOUSE - DAY",
+            "EXT. A GARDEN - NIGHT",
+            "INT. A SHED - NIGHT",
+        ] {
+            input.push_str(&format!("{}\n\n", scene));
+            input.push_str(&"Someone does something that takes a line.\n\n".repeat(6));
+        }
+        let (_, fdoc) = crate::parser::parse_fountain(&input).unwrap();
+        let profile = crate::profile::LayoutProfile {
+            lines_per_page: 19,
+            ..crate::profile::LayoutProfile::default()
+        };
+        let pages =
+            crate::layout::paginate(&fdoc, crate::layout::ScriptFormat::Screenplay, &profile);
+        assert_eq!(pages.len(), 3);
+        let options = PdfOptions {
+            header,
+            footer: HeaderFooter::parse("{title}|Scenes {scenes}").unwrap(),
+            ..PdfOptions::default()
+        };
+        let decorations = PageDecorations::new(&fdoc, &options, &pages);
+        let texts = |parts: Vec<(genpdf::elements::Alignment, String)>| {
+            parts.into_iter().map(|(_, text)| text).collect::<Vec<_>>()
+        };
+        // No page number on the first page
+        assert_eq!(texts(decorations.header(0)), vec!["Blue Rev. 10/12/26"]);
+        assert_eq!(
+            texts(decorations.header(1)),
+            vec!["Blue Rev. 10/12/26", "2."]
+        );
+        assert_eq!(texts(decorations.footer(0)), vec!["Big Fish", "Scenes 1-2"]);
+        // Scene 2 carries on from the first page
+        assert_eq!(texts(decorations.footer(1)), vec!["Big Fish", "Scenes 2-3"]);
+        assert_eq!(texts(decorations.footer(2)), vec!["Big Fish", "Scenes 3"]);
+
+        // A locked script's pages keep their own numbers
+        let mut pages = pages;
+        pages[1].number = Some(String::from("1A"));
+        let decorations = PageDecorations::new(&fdoc, &options, &pages);
+        assert_eq!(
+            texts(decorations.header(1)),
+            vec!["Blue Rev. 10/12/26", "1A."]
+        );
+    }
+
+    #[test]
+    fn test_add_watermark() {
+        let mut doc = blank_pdf(2);
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        let page_content = doc.add_object(lopdf::Stream::new(dictionary! {}, b"1 g".to_vec()));
+        doc.get_object_mut(pages[0])
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Contents", page_content);
+        let fonts = vec![get_embedded_font("Courier Prime Bold.ttf")];
+        add_watermark(&mut doc, "Jane Doe", &fonts).unwrap();
+
+        for page_id in pages {
+            // The page's own content is wrapped in q / Q, then the watermark
+            let content = doc.get_page_content(page_id).unwrap();
+            let content = lopdf::content::Content::decode(&content).unwrap();
+            let operators: Vec<&str> = content
+                .operations
+                .iter()
+                .map(|op| op.operator.as_str())
+                .collect();
+            assert_eq!(operators.first(), Some(&"q"));
+            // Two bytes per glyph
+            let text = content.operations.iter().find(|op| op.operator == "Tj");
+            assert_eq!(text.unwrap().operands[0].as_str().unwrap().len(), 16);
+
+            // The font's embedded, with a way back to the text
+            let fonts = doc.get_page_fonts(page_id);
+            assert_eq!(fonts.len(), 1);
+            let font = fonts.values().next().unwrap();
+            assert_eq!(
+                font.get(b"Subtype").unwrap().as_name_str().unwrap(),
+                "Type0"
+            );
+            let to_unicode = font.get(b"ToUnicode").unwrap().as_reference().unwrap();
+            let to_unicode = doc.get_object(to_unicode).unwrap().as_stream().unwrap();
+            let to_unicode = String::from_utf8_lossy(&to_unicode.content).to_string();
+            // "J", "a", "n", "e", " ", "D", "o" (the "e" is only there once)
+            assert!(to_unicode.contains("7 beginbfchar"));
+            assert!(to_unicode.contains("<004A>"));
+            let cid_font = font.get(b"DescendantFonts").unwrap().as_array().unwrap()[0]
+                .as_reference()
+                .unwrap();
+            let descriptor = doc
+                .get_dictionary(cid_font)
+                .unwrap()
+                .get(b"FontDescriptor")
+                .unwrap()
+                .as_reference()
+                .unwrap();
+            assert!(doc.get_dictionary(descriptor).unwrap().has(b"FontFile2"));
+        }
+    }
+
+    #[test]
+    fn test_watermark_fallback_font() {
+        let mut doc = blank_pdf(1);
+        let fonts = vec![
+            get_embedded_font("Courier Prime Bold.ttf"),
+            std::fs::read("fonts/truetype/DejaVu Sans Mono/DejaVuSansMono.ttf").unwrap(),
+        ];
+        // Courier Prime doesn't have Greek
+        add_watermark(&mut doc, "Γιάννης Smith", &fonts).unwrap();
+        let page_id = doc.page_iter().next().unwrap();
+        assert_eq!(doc.get_page_fonts(page_id).len(), 2);
+        let content = doc.get_page_content(page_id).unwrap();
+        let content = lopdf::content::Content::decode(&content).unwrap();
+        let runs: Vec<usize> = content
+            .operations
+            .iter()
+            .filter(|op| op.operator == "Tj")
+            .map(|op| op.operands[0].as_str().unwrap().len() / 2)
+            .collect();
+        // "Γιάννης " then "Smith"
+        assert_eq!(runs, vec![8, 5]);
+    }
+
+    #[test]
+    fn test_watermark_errors() {
+        // A page whose resources are broken
+        let mut doc = blank_pdf(1);
+        let page_id = doc.page_iter().next().unwrap();
+        doc.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("Resources", dictionary! { "Font" => 1 });
+        let fonts = vec![get_embedded_font("Courier Prime Bold.ttf")];
+        assert!(add_watermark(&mut doc, "Jane Doe", &fonts).is_err());
+    }
+
+    #[test]
+    fn test_sheet_order() {
+        assert_eq!(
+            sheet_order(3, Imposition::TwoUp),
+            vec![[Some(0), Some(1)], [Some(2), None]]
+        );
+        // 6 pages are padded out to 8, with the blanks at the back
+        assert_eq!(
+            sheet_order(6, Imposition::Booklet),
+            vec![
+                [None, Some(0)],
+                [Some(1), None],
+                [Some(5), Some(2)],
+                [Some(3), Some(4)],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_impose() {
+        let entries = vec![OutlineEntry {
+            depth: 1,
+            title: String::from("1 INT. HOUSE - DAY"),
+            page: 2,
+            top: 72.0,
+        }];
+        let mut doc = blank_pdf(3);
+        add_outline(&mut doc, &entries, 3).unwrap();
+        impose(&mut doc, Imposition::Booklet, true).unwrap();
+
+        let sheets: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        assert_eq!(sheets.len(), 2);
+        for &sheet_id in &sheets {
+            let media_box = media_box(&doc, sheet_id).unwrap();
+            assert_eq!(media_box, [0.0, 0.0, 842.0, 595.0]);
+        }
+        // The front of the sheet has the blank page and the first page, and
+        // the back has the second and third
+        let xobjects = |sheet_id| {
+            let resources = doc.get_page_resources(sheet_id).0.unwrap();
+            let xobjects = resources.get(b"XObject").unwrap().as_dict().unwrap();
+            let mut names: Vec<String> = xobjects
+                .iter()
+                .map(|(name, _)| String::from_utf8(name.clone()).unwrap())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(xobjects(sheets[0]), vec!["Page1"]);
+        assert_eq!(xobjects(sheets[1]), vec!["Page2", "Page3"]);
+        let content = doc.get_page_content(sheets[1]).unwrap();
+        let content = lopdf::content::Content::decode(&content).unwrap();
+        let count = |operator| {
+            content
+                .operations
+                .iter()
+                .filter(|op| op.operator == operator)
+                .count()
+        };
+        assert_eq!(count("Do"), 2);
+        assert_eq!(count("S"), 10); // Crop and fold marks
+
+        // The outline entry goes to the sheet its page is on now
+        let outlines_id = doc
+            .catalog()
+            .unwrap()
+            .get(b"Outlines")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let outlines = doc.get_dictionary(outlines_id).unwrap();
+        let entry_id = outlines.get(b"First").unwrap().as_reference().unwrap();
+        let entry = doc.get_dictionary(entry_id).unwrap();
+        let dest = entry.get(b"Dest").unwrap().as_array().unwrap();
+        assert_eq!(dest[0].as_reference().unwrap(), sheets[1]);
+    }
+
+    #[test]
+    fn test_paper_size_from_name() {
+        assert_eq!(PaperSize::from_name("Legal"), Ok(PaperSize::Legal));
+        assert_eq!(PaperSize::from_name(" a5 "), Ok(PaperSize::A5));
+        assert_eq!(
+            PaperSize::from_name("150x230mm"),
+            Ok(PaperSize::Custom {
+                width: 150.0,
+                height: 230.0
+            })
+        );
+        assert_eq!(
+            PaperSize::from_name("6 x 9 in").map(|p| p.size()),
+            Ok((inches(6.0), inches(9.0)))
+        );
+        assert!(PaperSize::from_name("A3").is_err());
+        assert!(PaperSize::from_name("6x9").is_err());
+        assert!(PaperSize::from_name("0x9in").is_err());
+    }
+
+    #[test]
+    fn test_paper_geometry() {
+        // The text is centered on bigger paper, and A4 and Letter stay as
+        // they were
+        assert_eq!(PaperSize::A4.extra_margins(), (0.0, 0.0));
+        assert_eq!(PaperSize::Letter.extra_margins(), (0.0, 0.0));
+        assert_eq!(PaperSize::Legal.extra_margins(), (0.0, 29.5));
+        let tabloid = PaperSize::from_name("279x432mm").unwrap();
+        assert_eq!(tabloid.extra_margins(), (31.5, 67.5));
+        // Smaller paper gets the pages laid out on A4 and scaled down
+        assert_eq!(PaperSize::A5.layout_paper(), PaperSize::A4);
+        assert_eq!(PaperSize::B5.layout_paper(), PaperSize::A4);
+        assert_eq!(PaperSize::B5.extra_margins(), (0.0, 0.0));
+
+        // The footer has to fit above the bottom margin
+        let profile = crate::profile::LayoutProfile::feature();
+        assert!(PaperSize::Letter.check_text_fits(&profile).is_ok());
+        assert!(PaperSize::A5.check_text_fits(&profile).is_ok());
+        let profile = crate::profile::LayoutProfile {
+            lines_per_page: 57,
+            ..crate::profile::LayoutProfile::feature()
+        };
+        assert!(PaperSize::Letter.check_text_fits(&profile).is_err());
+        assert!(PaperSize::A4.check_text_fits(&profile).is_ok());
+    }
+
+    #[test]
+    fn test_paper_size_media_box() {
+        // The rendered pages come out the size of the paper, in points,
+        // whether genpdf lays them out on it or they get scaled down to fit
+        let render = |paper_size| {
+            let (_, fdoc) = crate::parser::parse_fountain("INT. HOUSE - DAY\n\nA man.\n").unwrap();
+            let options = PdfOptions {
+                paper_size: Some(paper_size),
+                ..PdfOptions::default()
+            };
+            let mut bytes = Vec::new();
+            create_pdf(fdoc, &options)
+                .unwrap()
+                .render(&mut bytes)
+                .unwrap();
+            let doc = lopdf::Document::load_mem(&bytes).unwrap();
+            let page_id = *doc.get_pages().values().next().unwrap();
+            let [_, _, width, height] = media_box(&doc, page_id).unwrap();
+            (width.round(), height.round())
+        };
+        assert_eq!(render(PaperSize::Letter), (612.0, 792.0));
+        assert_eq!(render(PaperSize::Legal), (612.0, 1008.0));
+        assert_eq!(render(PaperSize::A5), (420.0, 595.0));
+        let tabloid = PaperSize::from_name("279x432mm").unwrap();
+        assert_eq!(render(tabloid), (791.0, 1225.0));
+    }
+
+    #[test]
+    fn test_fit_to_paper() {
+        let mut doc = blank_pdf(2);
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        doc.get_object_mut(pages[0])
+            .and_then(Object::as_dict_mut)
+            .unwrap()
+            .set("TrimBox", vec![0.into(), 0.into(), 595.into(), 842.into()]);
+        // A5 is exactly half of A4
+        fit_to_paper(&mut doc, 595.0 / 2.0_f32.sqrt(), 842.0 / 2.0_f32.sqrt()).unwrap();
+        for page_id in pages {
+            let [_, _, width, height] = media_box(&doc, page_id).unwrap();
+            assert_eq!((width.round(), height.round()), (421.0, 595.0));
+            let page = doc.get_dictionary(page_id).unwrap();
+            assert!(!page.has(b"TrimBox"));
+            let content = doc.get_page_content(page_id).unwrap();
+            let content = lopdf::content::Content::decode(&content).unwrap();
+            let transform = &content.operations[1];
+            assert_eq!(transform.operator, "cm");
+            let scale = transform.operands[0].as_f64().unwrap();
+            assert!((scale - 0.5_f64.sqrt()).abs() < 0.001);
+            assert_eq!(content.operations.last().unwrap().operator, "Q");
+        }
     }
 }
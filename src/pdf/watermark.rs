@@ -0,0 +1,303 @@
+// A watermark, eg. "CONFIDENTIAL" or the name of the person a copy was sent
+// to, printed across every page. genpdf can't rotate text or make it
+// translucent, so we draw it onto the rendered PDF afterwards, with lopdf.
+// It's printed in the script's bold font, or a fallback font for any chars
+// that doesn't have, which get embedded again as subsets of just the
+// watermark's glyphs.
+
+use super::{get_font_metrics, get_glyph_ids, media_box, subset_font};
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+
+const FONT_NAME: &str = "ScenewriterWatermarkFont";
+const STATE_NAME: &str = "ScenewriterWatermark";
+const OPACITY: f32 = 0.15;
+const GREY: f32 = 0.5;
+const MAX_FONT_SIZE: f32 = 96.0;
+// Every glyph gets the same width, whatever font it's from
+const CHAR_WIDTH_EM: f32 = 0.6;
+const CAP_HEIGHT_EM: f32 = 0.57;
+
+/// Some of the watermark's text, all in one font: the font's index and the
+/// glyphs' ids in its subset
+type Run = (usize, Vec<u16>);
+
+/// A font's name in the pages' resources, and the font
+type FontResource = (String, ObjectId);
+
+/// Draw the text diagonally across the middle of the page, as big as fits
+fn watermark_operations(runs: &[Run], page: [f32; 4]) -> Vec<Operation> {
+    let [left, bottom, right, top] = page;
+    let (width, height) = (right - left, top - bottom);
+    let angle = height.atan2(width);
+    let (sin, cos) = angle.sin_cos();
+    let len = runs.iter().map(|(_, ids)| ids.len()).sum::<usize>().max(1) as f32;
+    let diagonal = width.hypot(height);
+    let font_size = (diagonal * 0.8 / (len * CHAR_WIDTH_EM)).min(MAX_FONT_SIZE);
+    // Centre the text on the middle of the page, along and across the line
+    let half_width = len * CHAR_WIDTH_EM * font_size / 2.0;
+    let half_height = CAP_HEIGHT_EM * font_size / 2.0;
+    let x = left + width / 2.0 - half_width * cos + half_height * sin;
+    let y = bottom + height / 2.0 - half_width * sin - half_height * cos;
+    let mut operations = vec![
+        Operation::new("Q", vec![]),
+        Operation::new("q", vec![]),
+        Operation::new("gs", vec![STATE_NAME.into()]),
+        Operation::new("g", vec![GREY.into()]),
+        Operation::new("BT", vec![]),
+        Operation::new(
+            "Tm",
+            vec![
+                cos.into(),
+                sin.into(),
+                (-sin).into(),
+                cos.into(),
+                x.into(),
+                y.into(),
+            ],
+        ),
+    ];
+    for (font, ids) in runs {
+        let bytes = ids.iter().flat_map(|id| id.to_be_bytes()).collect();
+        operations.push(Operation::new(
+            "Tf",
+            vec![font_name(*font).into(), font_size.into()],
+        ));
+        operations.push(Operation::new(
+            "Tj",
+            vec![Object::String(bytes, StringFormat::Hexadecimal)],
+        ));
+    }
+    operations.push(Operation::new("ET", vec![]));
+    operations.push(Operation::new("Q", vec![]));
+    operations
+}
+
+fn font_name(font: usize) -> String {
+    format!("{}{}", FONT_NAME, font)
+}
+
+/// The CMap that maps the glyphs back to text, so that the watermark can be
+/// copied and searched
+fn to_unicode(glyphs: &[(u16, char)]) -> Vec<u8> {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n\
+         12 dict begin\n\
+         begincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n\
+         /CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    // A bfchar section can only have 100 entries
+    for chunk in glyphs.chunks(100) {
+        cmap += &format!("{} beginbfchar\n", chunk.len());
+        for (id, c) in chunk {
+            let text: String = c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap += &format!("<{:04X}> <{}>\n", id, text);
+        }
+        cmap += "endbfchar\n";
+    }
+    cmap += "endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n";
+    cmap.into_bytes()
+}
+
+/// Embed a subset of the font with just the given glyphs, whose ids in the
+/// subset are their indexes in the list. Glyph 0 has to come first.
+fn add_font(
+    doc: &mut Document,
+    name: &str,
+    data: &[u8],
+    glyph_ids: &[u16],
+    glyphs: &[(u16, char)],
+) -> Result<ObjectId, String> {
+    let metrics = get_font_metrics(data)?;
+    let scale = |units: f32| (units * 1000.0 / metrics.units_per_em).round() as i64;
+    let subset = subset_font(data, glyph_ids)?;
+    let subset_len = subset.len() as i64;
+    let file_id = doc.add_object(Stream::new(dictionary! { "Length1" => subset_len }, subset));
+    let char_width = (CHAR_WIDTH_EM * 1000.0) as i64;
+    let descriptor_id = doc.add_object(dictionary! {
+        "Type" => "FontDescriptor",
+        "FontName" => name,
+        "Flags" => 4,
+        "FontBBox" => vec![
+            0.into(),
+            scale(metrics.descent).into(),
+            char_width.into(),
+            scale(metrics.ascent).into(),
+        ],
+        "ItalicAngle" => 0,
+        "Ascent" => scale(metrics.ascent),
+        "Descent" => scale(metrics.descent),
+        "CapHeight" => (CAP_HEIGHT_EM * 1000.0) as i64,
+        "StemV" => 80,
+        "FontFile2" => file_id,
+    });
+    let cid_font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "CIDFontType2",
+        "BaseFont" => name,
+        "CIDSystemInfo" => dictionary! {
+            "Registry" => Object::string_literal("Adobe"),
+            "Ordering" => Object::string_literal("Identity"),
+            "Supplement" => 0,
+        },
+        "FontDescriptor" => descriptor_id,
+        "DW" => char_width,
+        "CIDToGIDMap" => "Identity",
+    });
+    let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, to_unicode(glyphs)));
+    Ok(doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => name,
+        "Encoding" => "Identity-H",
+        "DescendantFonts" => vec![cid_font_id.into()],
+        "ToUnicode" => to_unicode_id,
+    }))
+}
+
+/// Add the fonts for the text, and split it into runs of glyphs from the same
+/// font. Each char comes from the first font that has it.
+fn add_fonts(
+    doc: &mut Document,
+    text: &str,
+    fonts: &[Vec<u8>],
+) -> Result<(Vec<FontResource>, Vec<Run>), String> {
+    let mut chars: Vec<char> = text.chars().collect();
+    chars.sort_unstable();
+    chars.dedup();
+    let font_glyph_ids = fonts
+        .iter()
+        .map(|data| get_glyph_ids(data, &chars))
+        .collect::<Result<Vec<_>, String>>()?;
+    // Each font's glyphs, as (glyph id, char)
+    let mut font_glyphs = vec![Vec::new(); fonts.len()];
+    for (i, c) in chars.iter().enumerate() {
+        match font_glyph_ids
+            .iter()
+            .enumerate()
+            .find_map(|(font, ids)| Some((font, ids[i]?)))
+        {
+            Some((font, id)) => font_glyphs[font].push((id, *c)),
+            None => eprintln!(
+                "Warning: No font has a glyph for '{}' (U+{:04X}), so it's left out of the watermark",
+                c,
+                *c as u32
+            ),
+        }
+    }
+
+    let mut resources = Vec::new();
+    // Each char's font, and its glyph's id in that font's subset
+    let mut subset_ids = std::collections::HashMap::new();
+    for (font, glyphs) in font_glyphs.iter_mut().enumerate() {
+        if glyphs.is_empty() {
+            continue;
+        }
+        glyphs.sort_unstable();
+        let mut glyph_ids = vec![0];
+        let mut subset_glyphs = Vec::new();
+        for (id, c) in glyphs.iter() {
+            if glyph_ids.last() != Some(id) {
+                glyph_ids.push(*id);
+            }
+            subset_glyphs.push(((glyph_ids.len() - 1) as u16, *c));
+        }
+        for (id, c) in &subset_glyphs {
+            subset_ids.insert(*c, (font, *id));
+        }
+        let name = font_name(font);
+        let font_id = add_font(doc, &name, &fonts[font], &glyph_ids, &subset_glyphs)?;
+        resources.push((name, font_id));
+    }
+
+    let mut runs: Vec<Run> = Vec::new();
+    for (font, id) in text.chars().filter_map(|c| subset_ids.get(&c)) {
+        match runs.last_mut() {
+            Some((last_font, ids)) if last_font == font => ids.push(*id),
+            _ => runs.push((*font, vec![*id])),
+        }
+    }
+    Ok((resources, runs))
+}
+
+fn add_resource(
+    doc: &mut Document,
+    page_id: ObjectId,
+    kind: &str,
+    name: &str,
+    id: ObjectId,
+) -> Result<(), String> {
+    let error = |e: lopdf::Error| format!("Couldn't add the watermark to a page ({})", e);
+    let resources = doc
+        .get_or_create_resources(page_id)
+        .and_then(Object::as_dict_mut)
+        .map_err(error)?;
+    // The fonts etc. might be in a dictionary of their own, shared by pages
+    let dict_id = match resources.get(kind.as_bytes()) {
+        Ok(Object::Reference(dict_id)) => Some(*dict_id),
+        Ok(_) => None,
+        Err(_) => {
+            resources.set(kind, Dictionary::new());
+            None
+        }
+    };
+    let dict = match dict_id {
+        Some(dict_id) => doc.get_object_mut(dict_id),
+        None => doc
+            .get_or_create_resources(page_id)
+            .and_then(Object::as_dict_mut)
+            .and_then(|resources| resources.get_mut(kind.as_bytes())),
+    };
+    dict.and_then(Object::as_dict_mut)
+        .map_err(error)?
+        .set(name, id);
+    Ok(())
+}
+
+/// Print the watermark on top of everything on every page, in the first of
+/// the fonts that has each char. The page's own content gets wrapped in
+/// q / Q, so that whatever graphics state it leaves behind doesn't affect the
+/// watermark.
+pub(super) fn add_watermark(
+    doc: &mut Document,
+    text: &str,
+    fonts: &[Vec<u8>],
+) -> Result<(), String> {
+    let (font_ids, runs) = add_fonts(doc, text, fonts)?;
+    let state_id = doc.add_object(dictionary! {
+        "Type" => "ExtGState",
+        "ca" => OPACITY,
+        "CA" => OPACITY,
+    });
+    let save_id = doc.add_object(Stream::new(dictionary! {}, b"q\n".to_vec()));
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    for page_id in pages {
+        let page = media_box(doc, page_id).ok_or("Couldn't find the size of a page of the PDF")?;
+        let content = Content {
+            operations: watermark_operations(&runs, page),
+        };
+        let content = content
+            .encode()
+            .map_err(|e| format!("Couldn't draw the watermark ({})", e))?;
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let mut contents = vec![Object::from(save_id)];
+        contents.extend(doc.get_page_contents(page_id).into_iter().map(Object::from));
+        contents.push(content_id.into());
+        doc.get_object_mut(page_id)
+            .and_then(Object::as_dict_mut)
+            .map_err(|e| format!("Couldn't read a page of the PDF ({})", e))?
+            .set("Contents", contents);
+        for (name, font_id) in &font_ids {
+            add_resource(doc, page_id, "Font", name, *font_id)?;
+        }
+        add_resource(doc, page_id, "ExtGState", STATE_NAME, state_id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+// Page geometry for PDF output.
+//
+// A layout profile says where each kind of element goes on the page, in the
+// same lines and character columns the layout module works in. There are
+// built-in profiles for the usual formats, or a profile can be loaded from a
+// TOML file, eg:
+//
+//     font_size = 12
+//     lines_per_page = 55
+//     paper = "Letter"
+//
+//     [margins]
+//     top = 0.5
+//     right = 1.0
+//     bottom = 0.5
+//     left = 1.5
+//
+//     [dialogue]
+//     indent = 10
+//     width = 35
+//     line_spacing = 2
+//
+// Anything that isn't in the file comes from the feature profile.
+
+use crate::layout::MIN_LINES_EITHER_SIDE;
+use crate::pdf::PaperSize;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Where an element's text goes. Indent and width are in character columns,
+/// counted from the left margin. Centered text is centered within the width.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ElementLayout {
+    pub indent: usize,
+    pub width: usize,
+    #[serde(default = "single_spaced")]
+    pub line_spacing: usize, // 1 for single spaced, 2 for double spaced etc.
+    #[serde(default)]
+    pub centered: bool,
+}
+
+fn single_spaced() -> usize {
+    1
+}
+
+impl ElementLayout {
+    fn new(indent: usize, width: usize) -> ElementLayout {
+        ElementLayout {
+            indent,
+            width,
+            line_spacing: 1,
+            centered: false,
+        }
+    }
+
+    fn centered(self) -> ElementLayout {
+        ElementLayout {
+            centered: true,
+            ..self
+        }
+    }
+
+    fn double_spaced(self) -> ElementLayout {
+        ElementLayout {
+            line_spacing: 2,
+            ..self
+        }
+    }
+
+    /// The column just after the last one the element can use
+    pub fn right_edge(&self) -> usize {
+        self.indent + self.width
+    }
+
+    /// Where a line of `len` chars starts
+    pub fn line_indent(&self, len: usize) -> usize {
+        match self.centered {
+            true => self.indent + self.width.saturating_sub(len) / 2,
+            false => self.indent,
+        }
+    }
+}
+
+/// Page margins, in inches. The page number goes at the top margin, and the
+/// text starts a few lines below it.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Margins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutProfile {
+    pub font_size: u8,
+    pub lines_per_page: usize,
+    pub margins: Margins,
+    pub scene_heading: ElementLayout,
+    pub action: ElementLayout,
+    pub character: ElementLayout,
+    pub dialogue: ElementLayout,
+    pub parenthetical: ElementLayout,
+    pub transition: ElementLayout, // Right aligned, so only the right edge matters
+    // The paper, if it isn't given on the command line or the title page
+    pub paper: Option<PaperSize>,
+}
+
+impl Default for LayoutProfile {
+    fn default() -> LayoutProfile {
+        LayoutProfile::feature()
+    }
+}
+
+impl LayoutProfile {
+    /// The standard feature screenplay format. At 12pt, Courier gives us 10
+    /// characters and 6 lines to the inch, and a page has 55 lines of text
+    /// below the page number.
+    pub fn feature() -> LayoutProfile {
+        LayoutProfile {
+            font_size: 12,
+            lines_per_page: 55,
+            margins: Margins {
+                top: 0.5,
+                right: 1.0,
+                bottom: 0.5,
+                left: 1.5,
+            },
+            scene_heading: ElementLayout::new(0, 60),
+            action: ElementLayout::new(0, 60),
+            character: ElementLayout::new(22, 38),
+            dialogue: ElementLayout::new(10, 35),
+            parenthetical: ElementLayout::new(16, 20),
+            transition: ElementLayout::new(0, 60),
+            paper: None,
+        }
+    }
+
+    /// Multi-camera sitcom format, with double spaced dialogue
+    pub fn multicam() -> LayoutProfile {
+        LayoutProfile {
+            character: ElementLayout::new(25, 35),
+            dialogue: ElementLayout::new(15, 35).double_spaced(),
+            parenthetical: ElementLayout::new(20, 25).double_spaced(),
+            ..LayoutProfile::feature()
+        }
+    }
+
+    /// Stage play format, where the dialogue runs the full width of the page,
+    /// character names are centered and stage directions are indented.
+    pub fn stage() -> LayoutProfile {
+        LayoutProfile {
+            action: ElementLayout::new(20, 40),
+            character: ElementLayout::new(0, 60).centered(),
+            dialogue: ElementLayout::new(0, 60),
+            parenthetical: ElementLayout::new(20, 40),
+            ..LayoutProfile::feature()
+        }
+    }
+
+    /// Two column AV script format, with the video on the left and the audio
+    /// on the right.
+    pub fn av() -> LayoutProfile {
+        LayoutProfile {
+            scene_heading: ElementLayout::new(0, 28),
+            action: ElementLayout::new(0, 28),
+            character: ElementLayout::new(32, 28),
+            dialogue: ElementLayout::new(32, 28),
+            parenthetical: ElementLayout::new(34, 26),
+            transition: ElementLayout::new(0, 28),
+            ..LayoutProfile::feature()
+        }
+    }
+
+    /// Radio script format, with the cue numbers in the margin, names next to
+    /// them and the speeches, sound cues and directions lined up beside those.
+    pub fn radio() -> LayoutProfile {
+        LayoutProfile {
+            action: ElementLayout::new(20, 40),
+            character: ElementLayout::new(4, 14),
+            dialogue: ElementLayout::new(20, 40),
+            parenthetical: ElementLayout::new(22, 38),
+            ..LayoutProfile::feature()
+        }
+    }
+
+    /// Comic script format. Panel descriptions run the full width of the page,
+    /// with the numbered balloons and captions indented below them.
+    pub fn comic() -> LayoutProfile {
+        LayoutProfile {
+            character: ElementLayout::new(4, 14),
+            dialogue: ElementLayout::new(20, 40),
+            parenthetical: ElementLayout::new(22, 38),
+            ..LayoutProfile::feature()
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<LayoutProfile> {
+        match name {
+            "feature" => Some(LayoutProfile::feature()),
+            "multicam" => Some(LayoutProfile::multicam()),
+            "stage" => Some(LayoutProfile::stage()),
+            "av" => Some(LayoutProfile::av()),
+            "radio" => Some(LayoutProfile::radio()),
+            "comic" => Some(LayoutProfile::comic()),
+            _ => None,
+        }
+    }
+
+    pub fn from_toml(input: &str) -> Result<LayoutProfile, String> {
+        let profile: LayoutProfile =
+            toml::from_str(input).map_err(|e| format!("Invalid layout profile ({})", e))?;
+        if profile.font_size == 0 {
+            return Err("Invalid layout profile (font_size can't be 0)".into());
+        }
+        // lines_per_page doesn't include the header, so it just needs enough
+        // lines to split text across pages
+        if profile.lines_per_page < MIN_LINES_EITHER_SIDE {
+            return Err(format!(
+                "Invalid layout profile (lines_per_page must be at least {})",
+                MIN_LINES_EITHER_SIDE
+            ));
+        }
+        let elements = [
+            ("scene_heading", profile.scene_heading),
+            ("action", profile.action),
+            ("character", profile.character),
+            ("dialogue", profile.dialogue),
+            ("parenthetical", profile.parenthetical),
+            ("transition", profile.transition),
+        ];
+        if let Some((name, _)) = elements.iter().find(|(_, element)| element.width == 0) {
+            return Err(format!(
+                "Invalid layout profile ({} width can't be 0)",
+                name
+            ));
+        }
+        Ok(profile)
+    }
+
+    pub fn from_toml_file(path: &Path) -> Result<LayoutProfile, String> {
+        let input = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read layout profile {} ({})", path.display(), e))?;
+        LayoutProfile::from_toml(&input)
+    }
+}
+
+#[cfg(test)]
+mod tests;
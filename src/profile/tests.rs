@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use crate::profile::*;
+
+    #[test]
+    fn test_from_toml() {
+        let input = "
+font_size = 11
+paper = \"A5\"
+
+[dialogue]
+indent = 12
+width = 30
+line_spacing = 2
+";
+        let profile = LayoutProfile::from_toml(input).unwrap();
+        assert_eq!(profile.font_size, 11);
+        assert_eq!(profile.paper, Some(crate::pdf::PaperSize::A5));
+        assert_eq!(
+            profile.dialogue,
+            ElementLayout {
+                indent: 12,
+                width: 30,
+                line_spacing: 2,
+                centered: false,
+            }
+        );
+        // Everything else is the same as the feature profile
+        assert_eq!(profile.action, LayoutProfile::feature().action);
+        assert_eq!(profile.margins, LayoutProfile::feature().margins);
+    }
+
+    #[test]
+    fn test_from_toml_errors() {
+        assert!(LayoutProfile::from_toml("font_sise = 11").is_err());
+        assert!(LayoutProfile::from_toml("[action]\nindent = 2").is_err());
+        assert!(LayoutProfile::from_toml("lines_per_page = 0").is_err());
+        assert!(LayoutProfile::from_toml("lines_per_page = 1").is_err());
+        assert!(LayoutProfile::from_toml("lines_per_page = 2").is_ok());
+        assert!(LayoutProfile::from_toml("[dialogue]\nindent = 10\nwidth = 0").is_err());
+        assert!(LayoutProfile::from_toml("paper = \"A3\"").is_err());
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert_eq!(
+            LayoutProfile::builtin("feature"),
+            Some(LayoutProfile::feature())
+        );
+        assert_eq!(
+            LayoutProfile::builtin("multicam")
+                .unwrap()
+                .dialogue
+                .line_spacing,
+            2
+        );
+        assert_eq!(LayoutProfile::builtin("sitcom"), None);
+    }
+}
@@ -0,0 +1,257 @@
+// Comparing a script with an earlier draft, for production revisions. The
+// elements are matched up with a longest common subsequence diff, and every
+// element that's new or different gets its lines marked with an asterisk in
+// the margin. Within an element that's been changed, the words are diffed
+// too, and only the lines with a changed word on them get marked.
+
+use crate::document::{FarceDocument, FarceElement, SceneHeading};
+use crate::inline_parser::parse_inline;
+use std::ops::Range;
+
+/// How an element differs from the earlier draft
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Change {
+    #[default]
+    Unchanged,
+    Added,
+    Modified(Vec<Range<usize>>), // The chars of `element_text` that are new or different
+}
+
+/// Text without any markup, as it gets printed
+fn plain_text(text: &str) -> String {
+    match parse_inline(text) {
+        Ok((_, expressions)) => expressions.iter().map(|e| e.as_text()).collect(),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// An element's text, before the format does anything to it. Dialogue is
+/// the character cue, then a newline, then the speech. The lines printed
+/// from an element say which of these chars they came from.
+pub fn element_text(element: &FarceElement) -> Vec<char> {
+    let text = match element {
+        FarceElement::FDialogue(dialogue) => format!(
+            "{}\n{}",
+            dialogue.character_line_as_text(),
+            plain_text(&dialogue.text)
+        ),
+        FarceElement::FSceneHeading(scene_heading) => scene_heading.heading_text(),
+        FarceElement::FAction(action) => plain_text(&action.text),
+        FarceElement::FSection(section) => section.text.clone(),
+        FarceElement::FPageBreak => String::new(),
+    };
+    text.chars().collect()
+}
+
+/// The words in some text, and where each one is
+fn words(chars: &[char]) -> Vec<(String, Range<usize>)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for i in 0..=chars.len() {
+        let at_space = i == chars.len() || chars[i].is_whitespace();
+        match (start, at_space) {
+            (None, false) => start = Some(i),
+            (Some(first), true) => {
+                words.push((chars[first..i].iter().collect(), first..i));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// The words of the new text that are new or different. Where words have
+/// been taken out, the word after them gets marked instead (or the one
+/// before, at the end).
+fn changed_words(old: &[char], new: &[char]) -> Vec<Range<usize>> {
+    let old: Vec<String> = words(old).into_iter().map(|(word, _)| word).collect();
+    let (new, ranges): (Vec<String>, Vec<Range<usize>>) = words(new).into_iter().unzip();
+    let mut changed: Vec<Range<usize>> = Vec::new();
+    // Whether words have been taken out since the last word that was kept,
+    // and whether anything's been put in their place
+    let (mut deleted, mut inserted) = (false, false);
+    for edit in diff(&old, &new) {
+        match edit {
+            Edit::Delete(_) => deleted = true,
+            Edit::Insert(j) => {
+                inserted = true;
+                changed.push(ranges[j].clone());
+            }
+            Edit::Keep(j) => {
+                if deleted && !inserted {
+                    changed.push(ranges[j].clone());
+                }
+                (deleted, inserted) = (false, false);
+            }
+        }
+    }
+    if deleted && !inserted {
+        changed.extend(ranges.last().cloned());
+    }
+    changed.dedup();
+    changed
+}
+
+impl Change {
+    /// Whether a printed line needs an asterisk, given which chars of its
+    /// element it shows. Lines that the layout has added, like "(MORE)",
+    /// don't have any.
+    pub fn is_revised(&self, source: Option<&Range<usize>>) -> bool {
+        match (self, source) {
+            (_, None) | (Change::Unchanged, _) => false,
+            (Change::Added, Some(_)) => true,
+            (Change::Modified(changed), Some(source)) => changed
+                .iter()
+                .any(|r| r.start < source.end && source.start < r.end),
+        }
+    }
+}
+
+const SCENE_HEADING: u8 = 1;
+
+/// The kind of element, and its text as written, for telling whether two
+/// elements are the same
+fn element_key(element: &FarceElement) -> (u8, String) {
+    let (kind, text) = match element {
+        FarceElement::FDialogue(dialogue) => (
+            0,
+            format!("{}\n{}", dialogue.character_line_as_text(), dialogue.text),
+        ),
+        FarceElement::FSceneHeading(scene_heading) => (SCENE_HEADING, scene_heading.heading_text()),
+        FarceElement::FAction(action) => (2, action.text.clone()),
+        FarceElement::FSection(section) => (3, format!("{} {}", section.depth, section.text)),
+        FarceElement::FPageBreak => (4, String::new()),
+    };
+    (kind, text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Edit {
+    Keep(usize), // Index in the new list
+    Delete(usize),
+    Insert(usize),
+}
+
+/// The edits that turn `old` into `new`, in order
+fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    // Only the middle part, between anything that's the same at the start
+    // and the end, needs the full table
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+    let (m, n) = (old_middle.len(), new_middle.len());
+    // lengths[i][j] is the length of the longest common subsequence of
+    // old_middle[i..] and new_middle[j..]
+    let mut lengths = vec![0_u32; (m + 1) * (n + 1)];
+    for i in (0..m).rev() {
+        for j in (0..n).rev() {
+            lengths[i * (n + 1) + j] = match old_middle[i] == new_middle[j] {
+                true => lengths[(i + 1) * (n + 1) + j + 1] + 1,
+                false => lengths[(i + 1) * (n + 1) + j].max(lengths[i * (n + 1) + j + 1]),
+            };
+        }
+    }
+    let mut edits: Vec<Edit> = (0..prefix).map(Edit::Keep).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < m || j < n {
+        if i < m && j < n && old_middle[i] == new_middle[j] {
+            edits.push(Edit::Keep(prefix + j));
+            i += 1;
+            j += 1;
+        } else if j < n
+            && (i == m || lengths[i * (n + 1) + j + 1] >= lengths[(i + 1) * (n + 1) + j])
+        {
+            edits.push(Edit::Insert(prefix + j));
+            j += 1;
+        } else {
+            edits.push(Edit::Delete(prefix + i));
+            i += 1;
+        }
+    }
+    edits.extend((new.len() - suffix..new.len()).map(Edit::Keep));
+    edits
+}
+
+/// A revised script, with what's changed in each of its elements
+pub struct Revision {
+    pub document: FarceDocument,
+    pub changes: Vec<Change>, // One for each element
+}
+
+impl Revision {
+    fn push(&mut self, element: FarceElement, change: Change) {
+        self.document.elements.push(element);
+        self.changes.push(change);
+    }
+}
+
+/// Compare a script with an earlier draft. Any scenes that have been cut are
+/// kept as "OMITTED" placeholders, so the other scenes keep their numbers.
+pub fn compare(earlier: &FarceDocument, current: FarceDocument) -> Revision {
+    let old_keys: Vec<_> = earlier.elements.iter().map(element_key).collect();
+    let new_keys: Vec<_> = current.elements.iter().map(element_key).collect();
+    let edits = diff(&old_keys, &new_keys);
+
+    let mut new_elements: Vec<Option<FarceElement>> =
+        current.elements.into_iter().map(Some).collect();
+    let mut revision = Revision {
+        document: FarceDocument {
+            title_page: current.title_page,
+            elements: Vec::new(),
+        },
+        changes: Vec::new(),
+    };
+    // Go through each run of deletions and insertions between the elements
+    // that are kept
+    let mut edits = edits.into_iter().peekable();
+    while edits.peek().is_some() {
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+        while let Some(edit) = edits.next_if(|edit| !matches!(edit, Edit::Keep(_))) {
+            match edit {
+                Edit::Delete(i) => deleted.push(i),
+                Edit::Insert(j) => inserted.push(j),
+                Edit::Keep(_) => {}
+            }
+        }
+        let scenes = |indices: &[usize], keys: &[(u8, String)]| {
+            indices
+                .iter()
+                .filter(|&&i| keys[i].0 == SCENE_HEADING)
+                .count()
+        };
+        let omitted = scenes(&deleted, &old_keys).saturating_sub(scenes(&inserted, &new_keys));
+        for j in inserted {
+            // Pair it up with the first deleted element of the same kind, if
+            // there is one
+            let change = match deleted.iter().position(|&i| old_keys[i].0 == new_keys[j].0) {
+                Some(position) => {
+                    let i = deleted.remove(position);
+                    let old = element_text(&earlier.elements[i]);
+                    let new = element_text(new_elements[j].as_ref().unwrap());
+                    Change::Modified(changed_words(&old, &new))
+                }
+                None => Change::Added,
+            };
+            revision.push(new_elements[j].take().unwrap(), change);
+        }
+        for _ in 0..omitted {
+            let placeholder = FarceElement::FSceneHeading(SceneHeading::omitted());
+            revision.push(placeholder, Change::Added);
+        }
+        if let Some(Edit::Keep(j)) = edits.next() {
+            revision.push(new_elements[j].take().unwrap(), Change::Unchanged);
+        }
+    }
+    revision
+}
+
+#[cfg(test)]
+mod tests;
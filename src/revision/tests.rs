@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use crate::document::FarceElement;
+    use crate::parser::parse_fountain;
+    use crate::revision::*;
+
+    fn compare_drafts(earlier: &str, current: &str) -> Revision {
+        let (_, earlier) = parse_fountain(earlier).unwrap();
+        let (_, current) = parse_fountain(current).unwrap();
+        compare(&earlier, current)
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let draft = "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox.\n";
+        let revision = compare_drafts(draft, draft);
+        assert_eq!(revision.document.elements.len(), 2);
+        assert!(revision.changes.iter().all(|c| *c == Change::Unchanged));
+    }
+
+    #[test]
+    fn test_added_and_modified() {
+        let revision = compare_drafts(
+            "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox. It sits.\n\nA dog.\n",
+            "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox. It runs.\n\nA cat.\n\nA dog.\n",
+        );
+        assert_eq!(
+            revision.changes,
+            vec![
+                Change::Unchanged,
+                Change::Modified(vec![10..15]), // "runs."
+                Change::Added,
+                Change::Unchanged,
+            ]
+        );
+        assert!(revision.changes[1].is_revised(Some(&(0..15))));
+        assert!(revision.changes[1].is_revised(Some(&(12..13))));
+        assert!(!revision.changes[1].is_revised(Some(&(0..6))));
+        // Lines the layout adds, like "(MORE)", are never revised
+        assert!(!revision.changes[1].is_revised(None));
+        assert!(!revision.changes[2].is_revised(None));
+        assert!(revision.changes[2].is_revised(Some(&(0..6))));
+    }
+
+    #[test]
+    fn test_changed_words() {
+        // A word that's also somewhere else in the earlier version
+        let revision = compare_drafts(
+            "Title: Test\n\nThe dog barks. The cat sleeps.\n",
+            "Title: Test\n\nThe dog sleeps. The cat sleeps.\n",
+        );
+        assert_eq!(revision.changes, vec![Change::Modified(vec![8..15])]);
+
+        // Words taken out mark the word after them, or the last word
+        let revision = compare_drafts(
+            "Title: Test\n\nA big red fox.\n\nA fox sleeps soundly.\n",
+            "Title: Test\n\nA fox.\n\nA fox sleeps.\n",
+        );
+        assert_eq!(
+            revision.changes,
+            vec![Change::Modified(vec![2..6]), Change::Modified(vec![6..13]),]
+        );
+
+        // Dialogue's text starts with the cue
+        let revision = compare_drafts(
+            "Title: Test\n\nMARY\nHello there.\n",
+            "Title: Test\n\nMARY\nHello you.\n",
+        );
+        assert_eq!(revision.changes, vec![Change::Modified(vec![11..15])]);
+    }
+
+    #[test]
+    fn test_cut_scene_is_omitted() {
+        let revision = compare_drafts(
+            "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox.\n\nEXT. A GARDEN - NIGHT\n\nA dog.\n\nINT. A SHED - NIGHT\n\nDust.\n",
+            "Title: Test\n\nINT. A HOUSE - DAY\n\nA fox.\n\nINT. A SHED - NIGHT\n\nDust.\n",
+        );
+        let headings: Vec<String> = revision
+            .document
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                FarceElement::FSceneHeading(heading) => Some(heading.heading_text()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            headings,
+            vec!["INT. A HOUSE - DAY", "OMITTED", "INT. A SHED - NIGHT"]
+        );
+        assert_eq!(revision.changes[2], Change::Added);
+    }
+}
@@ -1,9 +1,12 @@
 use crate::constants::DEFAULT_NUM_SPEAKER_STATS;
 use crate::{
     document::{FarceDocument, FarceElement},
+    layout::{lay_out, Layout, PaginateOptions, ScriptFormat},
+    profile::LayoutProfile,
     utils::print_underlined,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
 
 #[derive(Debug, Copy, Clone)]
 pub struct CharacterStats {
@@ -36,8 +39,184 @@ pub fn get_character_stats(document: &FarceDocument) -> HashMap<String, Characte
     character_stats
 }
 
-pub fn print_stats(document: &FarceDocument) {
+fn print_comic_page_stats(document: &FarceDocument) {
+    println!("Pages");
+    println!("=====");
+    for page in document.get_comic_pages() {
+        println!(
+            "{}: {} panels, {} balloons",
+            page.title.unwrap_or("(Before the first page)"),
+            page.num_panels(),
+            page.num_balloons()
+        );
+    }
+    println!();
+}
+
+/// The pages each character speaks on, as laid out
+fn character_pages(document: &FarceDocument, layout: &Layout) -> HashMap<String, BTreeSet<usize>> {
+    let mut pages: HashMap<String, BTreeSet<usize>> = HashMap::new();
+    for (element, placements) in document.elements.iter().zip(&layout.placements) {
+        if let FarceElement::FDialogue(d) = element {
+            pages
+                .entry(d.character_name.clone())
+                .or_default()
+                .extend(placements.iter().map(|p| p.page));
+        }
+    }
+    pages
+}
+
+/// Where a scene is in the laid out script, and how long it is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneReport {
+    pub number: usize,
+    pub heading: String,
+    pub first_page: String,
+    pub last_page: String,
+    pub eighths: usize, // Eighths of a page
+}
+
+impl SceneReport {
+    /// The pages it's on, eg. "page 3" or "pages 3-5"
+    fn pages(&self) -> String {
+        match self.first_page == self.last_page {
+            true => format!("page {}", self.first_page),
+            false => format!("pages {}-{}", self.first_page, self.last_page),
+        }
+    }
+}
+
+/// A length in eighths of a page as it's written on a schedule, eg. "1 3/8"
+pub fn format_eighths(eighths: usize) -> String {
+    match (eighths / 8, eighths % 8) {
+        (0, part) => format!("{}/8", part),
+        (pages, 0) => format!("{}", pages),
+        (pages, part) => format!("{} {}/8", pages, part),
+    }
+}
+
+/// Lay out the script, and report where each of its scenes went
+pub fn scene_reports(
+    document: &FarceDocument,
+    format: ScriptFormat,
+    profile: &LayoutProfile,
+) -> Vec<SceneReport> {
+    let layout = lay_out(document, format, profile, PaginateOptions::default());
+    laid_out_scene_reports(document, &layout, profile)
+}
+
+fn laid_out_scene_reports(
+    document: &FarceDocument,
+    layout: &Layout,
+    profile: &LayoutProfile,
+) -> Vec<SceneReport> {
+    let headings: Vec<String> = document
+        .elements
+        .iter()
+        .filter_map(|element| match element {
+            FarceElement::FSceneHeading(sh) => Some(sh.heading_text()),
+            _ => None,
+        })
+        .collect();
+    layout
+        .scenes()
+        .iter()
+        .map(|span| SceneReport {
+            number: span.scene,
+            heading: headings.get(span.scene - 1).cloned().unwrap_or_default(),
+            first_page: layout.pages[span.first.page].display_number(span.first.page),
+            last_page: layout.pages[span.last.page].display_number(span.last.page),
+            eighths: layout.eighths(span, profile.lines_per_page),
+        })
+        .collect()
+}
+
+fn print_scene_reports(reports: &[SceneReport]) {
+    println!("Scenes");
+    println!("======");
+    for report in reports {
+        println!(
+            "{}. {} ({}, {})",
+            report.number,
+            report.heading,
+            report.pages(),
+            format_eighths(report.eighths)
+        );
+    }
+    let total: usize = reports.iter().map(|r| r.eighths).sum();
+    println!("Total: {}", format_eighths(total));
+    println!();
+}
+
+fn csv_field(text: &str) -> String {
+    match text.contains(['"', ',', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Write the scene report as CSV, one row per scene
+pub fn write_scene_csv(reports: &[SceneReport], mut w: impl Write) -> Result<(), String> {
+    let mut output = String::from("scene,heading,first_page,last_page,eighths,length\n");
+    for report in reports {
+        output.push_str(&format!(
+            "{},{},{},{},{},{}\n",
+            report.number,
+            csv_field(&report.heading),
+            csv_field(&report.first_page),
+            csv_field(&report.last_page),
+            report.eighths,
+            format_eighths(report.eighths)
+        ));
+    }
+    w.write_all(output.as_bytes())
+        .map_err(|e| format!("Couldn't write CSV ({})", e))
+}
+
+/// Write the scene report as a JSON array, one object per scene
+pub fn write_scene_json(reports: &[SceneReport], mut w: impl Write) -> Result<(), String> {
+    let rows: Vec<String> = reports
+        .iter()
+        .map(|report| {
+            format!(
+                "  {{\"scene\": {}, \"heading\": {}, \"first_page\": {}, \"last_page\": {}, \"eighths\": {}, \"length\": {}}}",
+                report.number,
+                json_string(&report.heading),
+                json_string(&report.first_page),
+                json_string(&report.last_page),
+                report.eighths,
+                json_string(&format_eighths(report.eighths))
+            )
+        })
+        .collect();
+    let output = match rows.is_empty() {
+        true => String::from("[]\n"),
+        false => format!("[\n{}\n]\n", rows.join(",\n")),
+    };
+    w.write_all(output.as_bytes())
+        .map_err(|e| format!("Couldn't write JSON ({})", e))
+}
+
+pub fn print_stats(document: &FarceDocument, format: ScriptFormat, profile: &LayoutProfile) {
     let character_stats = get_character_stats(document);
+    let layout = lay_out(document, format, profile, PaginateOptions::default());
+    let character_pages = character_pages(document, &layout);
     let mut num_actions: usize = 0;
     let mut num_action_words: usize = 0;
     let mut num_dialogues: usize = 0;
@@ -64,7 +243,7 @@ pub fn print_stats(document: &FarceDocument) {
                     num_ext_scenes += 1;
                 }
             }
-            FarceElement::FPageBreak => {}
+            FarceElement::FSection(_) | FarceElement::FPageBreak => {}
         }
     }
 
@@ -82,8 +261,14 @@ pub fn print_stats(document: &FarceDocument) {
     println!("{} Scenes", num_scenes);
     println!("{} interior scenes", num_int_scenes);
     println!("{} exterior scenes", num_ext_scenes);
+    println!("{} pages", layout.pages.len());
 
     println!();
+    if format == ScriptFormat::Comic {
+        print_comic_page_stats(document);
+    } else if num_scenes > 0 {
+        print_scene_reports(&laid_out_scene_reports(document, &layout, profile));
+    }
     if sorted_speakers.len() > DEFAULT_NUM_SPEAKER_STATS {
         println!("Top {} characters", DEFAULT_NUM_SPEAKER_STATS);
         println!("================");
@@ -95,7 +280,14 @@ pub fn print_stats(document: &FarceDocument) {
         print_underlined(character_name);
         println!("Total words: {}", cs.num_words);
         println!("Dialogue sections: {}", cs.num_speeches);
+        println!(
+            "Pages: {}",
+            character_pages.get(*character_name).map_or(0, |p| p.len())
+        );
         println!();
     }
     println!();
 }
+
+#[cfg(test)]
+mod tests;
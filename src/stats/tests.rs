@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::ScriptFormat;
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+    use crate::stats::*;
+
+    #[test]
+    fn test_format_eighths() {
+        assert_eq!(format_eighths(1), "1/8");
+        assert_eq!(format_eighths(8), "1");
+        assert_eq!(format_eighths(19), "2 3/8");
+    }
+
+    fn reports() -> Vec<SceneReport> {
+        let mut input = String::from("Title: Test\n\nINT. A HOUSE - DAY\n\n");
+        input.push_str(&"Someone does something that takes a line.\n\n".repeat(30));
+        input.push_str("EXT. \"THE GARDEN\", LATER - NIGHT\n\nA fox.\n");
+        let (_, fdoc) = parse_fountain(&input).unwrap();
+        let profile = LayoutProfile {
+            lines_per_page: 40,
+            ..LayoutProfile::feature()
+        };
+        scene_reports(&fdoc, ScriptFormat::Screenplay, &profile)
+    }
+
+    #[test]
+    fn test_scene_reports() {
+        let reports = reports();
+        assert_eq!(reports.len(), 2);
+        // A full page, then about half of the next one
+        assert_eq!(reports[0].heading, "INT. A HOUSE - DAY");
+        assert_eq!(
+            (&*reports[0].first_page, &*reports[0].last_page),
+            ("1", "2")
+        );
+        assert_eq!(reports[0].eighths, 12);
+        // Three lines is less than an eighth, but it still counts as one
+        assert_eq!(
+            (&*reports[1].first_page, &*reports[1].last_page),
+            ("2", "2")
+        );
+        assert_eq!(reports[1].eighths, 1);
+    }
+
+    #[test]
+    fn test_scene_exports() {
+        let reports = reports();
+        let mut csv = Vec::new();
+        write_scene_csv(&reports, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "scene,heading,first_page,last_page,eighths,length
+1,INT. A HOUSE - DAY,1,2,12,1 4/8
+2,\"EXT. \"\"THE GARDEN\"\", LATER - NIGHT\",2,2,1,1/8
+"
+        );
+        let mut json = Vec::new();
+        write_scene_json(&reports, &mut json).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            r#"[
+  {"scene": 1, "heading": "INT. A HOUSE - DAY", "first_page": "1", "last_page": "2", "eighths": 12, "length": "1 4/8"},
+  {"scene": 2, "heading": "EXT. \"THE GARDEN\", LATER - NIGHT", "first_page": "2", "last_page": "2", "eighths": 1, "length": "1/8"}
+]
+"#
+        );
+    }
+}
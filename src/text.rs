@@ -0,0 +1,52 @@
+// Plain text output, for when a script needs to go somewhere that only takes
+// text, like a studio's cue system or an email. The pages are laid out just
+// as they are for PDF, in the same columns, with a form feed between them.
+
+use crate::document::FarceDocument;
+use crate::layout::{paginate, Line, ScriptFormat, HEADER_LINES};
+use crate::profile::LayoutProfile;
+use std::io::Write;
+
+fn line_text(line: &Line) -> String {
+    let mut text = String::new();
+    let mut indent = line.indent;
+    if let Some(number) = line.number {
+        let number = format!("{}.", number);
+        indent = indent.saturating_sub(number.chars().count());
+        text.push_str(&number);
+    }
+    text.push_str(&" ".repeat(indent));
+    text.push_str(&line.text());
+    text.trim_end().to_string()
+}
+
+/// Write the script's pages as plain text. The title page isn't included.
+pub fn write_text(
+    document: &FarceDocument,
+    mut w: impl Write,
+    format: ScriptFormat,
+    profile: &LayoutProfile,
+) -> Result<(), String> {
+    let pages = paginate(document, format, profile);
+    let mut output = String::new();
+    for (i, page) in pages.iter().enumerate() {
+        if i > 0 {
+            output.push('\u{c}');
+            // Right aligned with the edge of the action, as in the PDF
+            let number = format!("{}.", page.display_number(i));
+            let right_edge = profile.action.right_edge();
+            output.push_str(&" ".repeat(right_edge.saturating_sub(number.len())));
+            output.push_str(&number);
+        }
+        output.push_str(&"\n".repeat(HEADER_LINES));
+        for line in &page.lines {
+            output.push_str(&line_text(line));
+            output.push('\n');
+        }
+    }
+    w.write_all(output.as_bytes())
+        .map_err(|e| format!("Couldn't write text ({})", e))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use crate::layout::ScriptFormat;
+    use crate::parser::parse_fountain;
+    use crate::profile::LayoutProfile;
+    use crate::text::write_text;
+
+    #[test]
+    fn test_write_text() {
+        let input = "Title: Test
+
+INT. KITCHEN - NIGHT
+
+MARY
+Is that you, John?
+
+===
+
+SFX: A door slams.
+";
+        let (_, fdoc) = parse_fountain(input).unwrap();
+        let format = ScriptFormat::from_name("radio").unwrap();
+        let mut output = Vec::new();
+        write_text(&fdoc, &mut output, format, &LayoutProfile::radio()).unwrap();
+        let expected = format!(
+            "\n\n\nINT. KITCHEN - NIGHT\n\n1.  MARY:           Is that you, John?\n\u{c}{}2.\n\n\n1.  SFX:            A DOOR SLAMS.\n",
+            " ".repeat(58)
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}